@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
 use psybee::audio::{AudioDevice, AudioStimulus};
-use psybee::input::{Event, EventHandlingExt, EventKind, EventReceiver, EventVec, MouseButton};
+use psybee::clock::Clock;
+use psybee::input::{Event, EventHandlingExt, EventKind, EventReceiver, EventVec, KeyCombo, MouseButton};
 use psybee::visual::geometry::{Circle, Rectangle, Size, ToVertices, Transformable, Transformation2D};
 #[cfg(not(any(target_arch = "wasm32", target_os = "ios")))]
 use psybee::visual::stimuli::VideoStimulus;
@@ -152,6 +153,26 @@ impl PyWindow {
           });
     }
 
+    /// Summary of the frames that have been presented on the window so far.
+    ///
+    /// Returns
+    /// -------
+    /// stats : dict
+    ///   The number of frames, missed frames and missed vertical blanks, as well as the
+    ///   mean, standard deviation, minimum and maximum interval between flips (in seconds).
+    fn frame_stats(&self) -> std::collections::HashMap<&'static str, f64> {
+        let stats = self.0.frame_stats();
+
+        std::collections::HashMap::from([("n_frames", stats.n_frames as f64),
+                                         ("n_missed_frames", stats.n_missed_frames as f64),
+                                         ("n_missed_vblanks", stats.n_missed_vblanks as f64),
+                                         ("refresh_interval", stats.refresh_interval.as_secs_f64()),
+                                         ("mean_flip_interval", stats.mean_flip_interval.as_secs_f64()),
+                                         ("sd_flip_interval", stats.sd_flip_interval.as_secs_f64()),
+                                         ("min_flip_interval", stats.min_flip_interval.as_secs_f64()),
+                                         ("max_flip_interval", stats.max_flip_interval.as_secs_f64())])
+    }
+
     fn __repr__(&self) -> String {
         format!("{:?}", self.0)
     }
@@ -169,15 +190,16 @@ impl PyWindow {
     ///   The kind of event to listen for.
     /// callback : callable
     ///  The callback that will be called when the event occurs. The callback should take a single argument, an instance of `Event`.
+    ///  If the callback returns True, the event is considered handled, e.g. to keep the experiment running on `EventKind.ABORT_REQUESTED`.
     fn add_event_handler(&self, kind: PyEventKind, callback: Py<PyAny>, py: Python<'_>) {
+        let clock = self.0.clock().clone();
         let rust_callback_fn = move |event: Event| -> bool {
-            Python::with_gil(|py| -> PyResult<()> {
-                let pyevent = PyEvent(event);
-                callback.call1(py, (pyevent,))
-                        .expect("Error calling callback in event handler. Make sure the callback takes a single argument of type Event. Error");
-                Ok(())
-            }).unwrap();
-            false
+            Python::with_gil(|py| -> PyResult<bool> {
+                let pyevent = PyEvent(event, clock.clone());
+                let handled = callback.call1(py, (pyevent,))
+                                      .expect("Error calling callback in event handler. Make sure the callback takes a single argument of type Event. Error");
+                handled.is_truthy(py)
+            }).unwrap_or(false)
         };
 
         let self_wrapper = SendWrapper::new(self);
@@ -201,7 +223,7 @@ impl PyWindow {
     /// receiver : EventReceiver
     ///     The EventReceiver that was created.
     fn create_event_receiver(&self) -> PyEventReceiver {
-        PyEventReceiver(self.0.create_event_receiver())
+        PyEventReceiver(self.0.create_event_receiver(), self.0.clock().clone())
     }
 
     /// Set the visibility of the cursor.
@@ -233,13 +255,42 @@ impl PyWindow {
         self.0.height_px()
     }
 }
-py_wrap!(EventReceiver);
+/// Receives the events of a window. Event timestamps are measured on the
+/// experiment clock of the window.
+#[pyclass(name = "EventReceiver")]
+pub struct PyEventReceiver(EventReceiver, Clock);
 
 #[pymethods]
 impl PyEventReceiver {
     fn poll(&mut self) -> PyEventVec {
         PyEventVec { vec: self.0.poll(),
-                     i: Arc::new(Mutex::new(0)) }
+                     i: Arc::new(Mutex::new(0)),
+                     clock: self.1.clone() }
+    }
+
+    /// Wait for an event of one of the given kinds (and, for key events, one of
+    /// the given keys). Empty lists match any kind or key.
+    ///
+    /// Parameters
+    /// ----------
+    /// kinds : list[EventKind]
+    ///     The kinds of events to wait for.
+    /// keys : list[str]
+    ///     The keys to wait for.
+    /// timeout : float, optional
+    ///     The maximum time to wait in seconds.
+    ///
+    /// Returns
+    /// -------
+    /// event : Event or None
+    ///     The event, or None if the timeout elapsed.
+    #[pyo3(signature = (kinds, keys = Vec::new(), timeout = None))]
+    fn wait_for(&mut self, kinds: Vec<PyEventKind>, keys: Vec<String>, timeout: Option<f64>, py: Python<'_>) -> Option<PyEvent> {
+        let kinds = kinds.into_iter().map(Into::into).collect::<Vec<EventKind>>();
+        let timeout = timeout.map(std::time::Duration::from_secs_f64);
+
+        py.allow_threads(|| self.0.wait_for(&kinds, &keys, timeout))
+          .map(|event| PyEvent(event, self.1.clone()))
     }
 }
 
@@ -248,6 +299,7 @@ impl PyEventReceiver {
 pub struct PyEventVec {
     vec: EventVec,
     i: Arc<Mutex<usize>>,
+    clock: Clock,
 }
 
 #[pymethods]
@@ -262,12 +314,28 @@ impl PyEventVec {
         self.vec.iter().any(|key_event| key_event.key_released(key))
     }
 
+    /// Convinience method to check if a physical key (e.g. "KeyA" or "Space")
+    /// was pressed in the event vector, independent of the keyboard layout.
+    fn key_code_pressed(&self, key_code: &str) -> bool {
+        self.vec
+            .iter()
+            .any(|event| event.kind() == EventKind::KeyPress && event.key_code().and_then(|k| k.as_ref()).is_some_and(|k| format!("{:?}", k) == key_code))
+    }
+
+    /// Convinience method to check if a physical key (e.g. "KeyA" or "Space")
+    /// was released in the event vector, independent of the keyboard layout.
+    fn key_code_released(&self, key_code: &str) -> bool {
+        self.vec
+            .iter()
+            .any(|event| event.kind() == EventKind::KeyRelease && event.key_code().and_then(|k| k.as_ref()).is_some_and(|k| format!("{:?}", k) == key_code))
+    }
+
     fn __len__(&self) -> usize {
         self.vec.len()
     }
 
     fn __getitem__(&self, index: usize) -> PyEvent {
-        PyEvent(self.vec[index].clone())
+        PyEvent(self.vec[index].clone(), self.clock.clone())
     }
 
     fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
@@ -277,7 +345,7 @@ impl PyEventVec {
     fn __next__(slf: PyRef<'_, Self>) -> Option<PyEvent> {
         let mut i = slf.i.lock_blocking();
         if *i < slf.vec.len() {
-            let event = PyEvent(slf.vec[*i].clone());
+            let event = PyEvent(slf.vec[*i].clone(), slf.clock.clone());
             *i += 1;
             Some(event)
         } else {
@@ -352,6 +420,62 @@ impl PyExperimentManager {
         let self_wrapper = SendWrapper::new(self);
         py.allow_threads(move || PyWindow(self_wrapper.0.create_default_window()))
     }
+
+    /// The experiment clock. Its zero is the start of the session.
+    #[getter]
+    fn clock(&self) -> PyClock {
+        PyClock(self.0.clock().clone())
+    }
+
+    /// Disable or re-enable (with the default Escape key) aborting the
+    /// experiment with the keyboard.
+    fn set_abort_key_enabled(&self, enabled: bool) {
+        self.0.set_abort_key(enabled.then(KeyCombo::default));
+    }
+
+    /// Register a function that is called (without arguments) when the
+    /// experiment shuts down, e.g. to close log files.
+    fn add_shutdown_hook(&self, hook: Py<PyAny>) {
+        self.0.add_shutdown_hook(move || {
+                  Python::with_gil(|py| {
+                      if let Err(e) = hook.call0(py) {
+                          log::error!("Error calling shutdown hook: {}", e);
+                      }
+                  })
+              });
+    }
+}
+
+py_wrap!(Clock);
+
+#[pymethods]
+impl PyClock {
+    /// Create a new clock that starts now.
+    #[new]
+    fn new() -> Self {
+        PyClock(Clock::new())
+    }
+
+    /// Time since the zero point of the clock in seconds.
+    #[getter]
+    fn elapsed(&self) -> f64 {
+        self.0.elapsed_secs()
+    }
+
+    /// Set the zero point of the clock to now.
+    fn reset(&self) {
+        self.0.reset();
+    }
+
+    /// Return the sub-clock with the given name (e.g. "trial"). The sub-clock
+    /// is created the first time it is requested.
+    fn sub_clock(&self, name: &str) -> PyClock {
+        PyClock(self.0.sub_clock(name))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Clock at {:.3} s>", self.0.elapsed_secs())
+    }
 }
 
 // STIMULI
@@ -903,8 +1027,9 @@ impl PyScreenHeight {
     }
 }
 
+/// An event, together with the experiment clock its timestamp is measured on.
 #[pyclass(name = "Event")]
-pub struct PyEvent(Event);
+pub struct PyEvent(Event, Clock);
 
 #[pyclass(name = "EventKind", rename_all = "SCREAMING_SNAKE_CASE")]
 #[derive(Debug, Clone)]
@@ -924,6 +1049,15 @@ pub enum PyEventKind {
     CursorExited,
     TouchpadPress,
     MouseWheel,
+    ModifiersChanged,
+    DragStart,
+    DragMove,
+    DragEnd,
+    GamepadButtonPress,
+    GamepadButtonRelease,
+    GamepadAxisMotion,
+    ResponseBoxInput,
+    AbortRequested,
     Other,
 }
 
@@ -945,6 +1079,15 @@ impl From<EventKind> for PyEventKind {
             EventKind::CursorExited => PyEventKind::CursorExited,
             EventKind::TouchpadPress => PyEventKind::TouchpadPress,
             EventKind::MouseWheel => PyEventKind::MouseWheel,
+            EventKind::ModifiersChanged => PyEventKind::ModifiersChanged,
+            EventKind::DragStart => PyEventKind::DragStart,
+            EventKind::DragMove => PyEventKind::DragMove,
+            EventKind::DragEnd => PyEventKind::DragEnd,
+            EventKind::GamepadButtonPress => PyEventKind::GamepadButtonPress,
+            EventKind::GamepadButtonRelease => PyEventKind::GamepadButtonRelease,
+            EventKind::GamepadAxisMotion => PyEventKind::GamepadAxisMotion,
+            EventKind::ResponseBoxInput => PyEventKind::ResponseBoxInput,
+            EventKind::AbortRequested => PyEventKind::AbortRequested,
             EventKind::Other => PyEventKind::Other,
         }
     }
@@ -968,6 +1111,15 @@ impl From<PyEventKind> for EventKind {
             PyEventKind::CursorExited => EventKind::CursorExited,
             PyEventKind::TouchpadPress => EventKind::TouchpadPress,
             PyEventKind::MouseWheel => EventKind::MouseWheel,
+            PyEventKind::ModifiersChanged => EventKind::ModifiersChanged,
+            PyEventKind::DragStart => EventKind::DragStart,
+            PyEventKind::DragMove => EventKind::DragMove,
+            PyEventKind::DragEnd => EventKind::DragEnd,
+            PyEventKind::GamepadButtonPress => EventKind::GamepadButtonPress,
+            PyEventKind::GamepadButtonRelease => EventKind::GamepadButtonRelease,
+            PyEventKind::GamepadAxisMotion => EventKind::GamepadAxisMotion,
+            PyEventKind::ResponseBoxInput => EventKind::ResponseBoxInput,
+            PyEventKind::AbortRequested => EventKind::AbortRequested,
             PyEventKind::Other => EventKind::Other,
         }
     }
@@ -979,12 +1131,11 @@ impl PyEvent {
         format!("{:?}", self.0)
     }
 
+    /// Time of the event in seconds since the start of the experiment, i.e.
+    /// on the experiment clock.
     #[getter]
     fn timestamp(&self) -> f64 {
-        // convert to f64
-        // (seconds since
-        // epoch)
-        self.0.timestamp().duration_since(std::time::UNIX_EPOCH).expect("Time went backwards").as_secs_f64()
+        self.1.secs(*self.0.timestamp())
     }
 
     #[getter]
@@ -996,6 +1147,62 @@ impl PyEvent {
     fn position(&self) -> Option<(PySize, PySize)> {
         self.0.position().map(|p| (PySize(p.0.clone()), PySize(p.1.clone())))
     }
+
+    /// The key of a key event, taking the keyboard layout into account.
+    #[getter]
+    fn key(&self) -> Option<String> {
+        self.0.key().cloned()
+    }
+
+    /// The physical key of a key event (e.g. "KeyA" or "Space"), independent
+    /// of the keyboard layout.
+    #[getter]
+    fn key_code(&self) -> Option<String> {
+        self.0.key_code().and_then(|k| k.as_ref()).map(|k| format!("{:?}", k))
+    }
+
+    /// Whether a key event was generated by the key being held down.
+    #[getter]
+    fn repeat(&self) -> Option<bool> {
+        self.0.repeat().copied()
+    }
+
+    /// The position where a drag started.
+    #[getter]
+    fn start_position(&self) -> Option<(PySize, PySize)> {
+        self.0.start_position().map(|p| (PySize(p.0.clone()), PySize(p.1.clone())))
+    }
+
+    /// The velocity of the mouse cursor during a drag in pixels per second.
+    #[getter]
+    fn velocity(&self) -> Option<(f64, f64)> {
+        self.0.velocity().copied()
+    }
+
+    /// The gamepad button of a gamepad event (e.g. "South" or "DPadUp").
+    #[getter]
+    fn gamepad_button(&self) -> Option<String> {
+        self.0.gamepad_button().map(|b| format!("{:?}", b))
+    }
+
+    /// The axis (e.g. "LeftStickX") and value (-1.0 to 1.0) of a gamepad axis
+    /// event.
+    #[getter]
+    fn axis(&self) -> Option<(String, f32)> {
+        self.0.axis().map(|a| format!("{:?}", a)).zip(self.0.value().copied())
+    }
+
+    /// The byte received from a serial response box.
+    #[getter]
+    fn byte(&self) -> Option<u8> {
+        self.0.byte().copied()
+    }
+
+    /// The amount of scrolling of a mouse wheel event as (horizontal, vertical).
+    #[getter]
+    fn scroll_delta(&self) -> Option<(f32, f32)> {
+        self.0.horizontal().copied().zip(self.0.vertical().copied())
+    }
 }
 
 // #[pyo3::prelude::pyclass(name = "MouseButton")]
@@ -1049,6 +1256,7 @@ fn psybee_py(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     pyo3::prepare_freethreaded_python();
 
     m.add_class::<PyExperimentManager>()?;
+    m.add_class::<PyClock>()?;
     m.add_class::<PyMonitor>()?;
     m.add_class::<PyMainLoop>()?;
 
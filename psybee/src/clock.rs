@@ -0,0 +1,171 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Monotonic timestamps and clocks. Input events and frame presentations are
+//! timestamped with the same monotonic clock, so reaction times and onsets
+//! can be compared directly. The experiment clock (see
+//! `ExperimentManager::clock()`) defines t=0 for the session; each `MainLoop`
+//! starts a new one when it is created.
+//!
+//! Named sub-clocks (e.g. a trial or block clock) can be used to measure time
+//! relative to other events:
+//!
+//! ```no_run
+//! # fn run(em: &psybee::ExperimentManager, window: &psybee::visual::Window) {
+//! let trial_clock = em.clock().sub_clock("trial");
+//!
+//! let onset = window.present(window.get_frame());
+//! trial_clock.reset_to(onset.onset());
+//! // ...
+//! println!("{} s since the onset of the trial", trial_clock.elapsed_secs());
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::ops::{Add, Sub};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_lock::{Mutex, RwLock};
+use web_time::Instant;
+
+/// A point in time, measured with a monotonic clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(Instant);
+
+impl Timestamp {
+    /// Returns the current time.
+    pub fn now() -> Self {
+        Self(Instant::now())
+    }
+
+    /// Returns the underlying `Instant`.
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Returns the time elapsed since an earlier timestamp, or zero if
+    /// `earlier` is later than this timestamp.
+    pub fn duration_since(&self, earlier: Timestamp) -> Duration {
+        self.0.saturating_duration_since(earlier.0)
+    }
+
+    /// Returns the timestamp `duration` before this one, or None if it cannot
+    /// be represented (e.g. because it would be before the start of the
+    /// monotonic clock).
+    pub fn checked_sub(&self, duration: Duration) -> Option<Timestamp> {
+        self.0.checked_sub(duration).map(Self)
+    }
+
+    /// Returns the (signed) difference to another timestamp in seconds.
+    pub fn secs_since(&self, other: Timestamp) -> f64 {
+        if self.0 >= other.0 {
+            (self.0 - other.0).as_secs_f64()
+        } else {
+            -(other.0 - self.0).as_secs_f64()
+        }
+    }
+}
+
+impl From<Instant> for Timestamp {
+    fn from(instant: Instant) -> Self {
+        Self(instant)
+    }
+}
+
+impl From<Timestamp> for Instant {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, duration: Duration) -> Self::Output {
+        Self(self.0 + duration)
+    }
+}
+
+impl Sub<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, duration: Duration) -> Self::Output {
+        Self(self.0 - duration)
+    }
+}
+
+/// A clock that measures time relative to a zero point. Clones of a clock
+/// share the same zero point (and sub-clocks), so resetting one resets all of
+/// them.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    zero: Arc<RwLock<Instant>>,
+    sub_clocks: Arc<Mutex<HashMap<String, Clock>>>,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock {
+    /// Create a new clock that starts now.
+    pub fn new() -> Self {
+        Self { zero: Arc::new(RwLock::new(Instant::now())),
+               sub_clocks: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    /// Returns the zero point of the clock.
+    pub fn zero(&self) -> Timestamp {
+        Timestamp(*self.zero.read_blocking())
+    }
+
+    /// Returns the time elapsed since the zero point.
+    pub fn elapsed(&self) -> Duration {
+        self.zero.read_blocking().elapsed()
+    }
+
+    /// Returns the time elapsed since the zero point in seconds.
+    pub fn elapsed_secs(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+
+    /// Returns the time of the given timestamp on this clock in seconds. The
+    /// result is negative for timestamps before the zero point.
+    pub fn secs<T: Into<Timestamp>>(&self, timestamp: T) -> f64 {
+        timestamp.into().secs_since(self.zero())
+    }
+
+    /// Sets the zero point of the clock to now.
+    pub fn reset(&self) {
+        self.reset_to(Timestamp::now());
+    }
+
+    /// Sets the zero point of the clock to the given time, e.g. the onset of a
+    /// frame.
+    pub fn reset_to<T: Into<Timestamp>>(&self, timestamp: T) {
+        *self.zero.write_blocking() = timestamp.into().instant();
+    }
+
+    /// Returns the sub-clock with the given name, e.g. a trial or block clock.
+    /// The sub-clock is created (starting now) the first time it is requested;
+    /// later calls return the same clock. Sub-clocks are independent of this
+    /// clock, i.e. resetting one does not affect the other.
+    pub fn sub_clock(&self, name: &str) -> Clock {
+        self.sub_clocks
+            .lock_blocking()
+            .entry(name.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Returns the names of all sub-clocks.
+    pub fn sub_clock_names(&self) -> Vec<String> {
+        self.sub_clocks.lock_blocking().keys().cloned().collect()
+    }
+}
@@ -37,6 +37,20 @@ pub enum PsybeeError {
     #[error("{0}")]
     SerialPortError(#[from] serialport::Error),
 
+    // gamepad errors
+    #[cfg(feature = "gamepad")]
+    #[error("Failed to initialise gamepad input: {0}")]
+    GamepadError(String),
+
+    // marker stream errors
+    #[error("Marker stream error: {0}")]
+    MarkerStreamError(String),
+
+    // LSL errors
+    #[cfg(feature = "lsl")]
+    #[error("LSL error: {0}")]
+    LslError(#[from] ::lsl::Error),
+
     // image errors
     #[error("{0}")]
     ImageError(#[from] image::ImageError),
@@ -60,6 +74,22 @@ pub enum PsybeeError {
     // single image error
     #[error("Only one image was provided. This is currently not supported.")]
     SingleImageError,
+
+    // frame capture errors
+    #[error("Failed to capture frame: {0}")]
+    FrameCaptureError(String),
+
+    // trial handler errors
+    #[error("Invalid trial options: {0}")]
+    InvalidTrialOptionsError(String),
+
+    // staircase errors
+    #[error("Invalid staircase options: {0}")]
+    InvalidStaircaseOptionsError(String),
+
+    // psychometric function fitting errors
+    #[error("Failed to fit psychometric function: {0}")]
+    PsychometricFitError(String),
 }
 
 // macro that error with the given message
@@ -0,0 +1,186 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Gamepad and joystick input (requires the `gamepad` feature). Gamepad
+//! events are delivered through the same channels as keyboard and mouse
+//! events, i.e. to event receivers and event handlers of the window.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # fn run(window: &psybee::visual::Window) -> Result<(), psybee::errors::PsybeeError> {
+//! use psybee::input::gamepad::GamepadInput;
+//! use psybee::input::{EventKind, GamepadButton};
+//!
+//! // events are delivered as long as the GamepadInput is alive
+//! let _gamepads = GamepadInput::new(window)?;
+//!
+//! let mut receiver = window.create_event_receiver();
+//! let event = receiver.wait_for(&[EventKind::GamepadButtonPress], &[] as &[&str], None);
+//! if event.is_some_and(|event| event.gamepad_button_pressed(GamepadButton::South)) {
+//!     // ...
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use async_channel::bounded;
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use super::{Event, EventHandlingExt, GamepadAxis, GamepadButton};
+use crate::clock::Timestamp;
+use crate::errors::PsybeeError;
+use crate::visual::Window;
+
+/// How often the input thread checks whether it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Reads events from all connected gamepads and delivers them to a window.
+/// Gamepads that are connected later are picked up automatically. Reading
+/// stops when this is dropped.
+#[derive(Debug)]
+pub struct GamepadInput {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GamepadInput {
+    /// Start reading gamepad events and deliver them to the given window.
+    pub fn new(window: &Window) -> Result<Self, PsybeeError> {
+        let (init_sender, init_receiver) = bounded(1);
+        let running = Arc::new(AtomicBool::new(true));
+
+        let window = window.clone();
+        let running_clone = running.clone();
+
+        // gilrs is not Send on all platforms, so it is created on the input thread
+        let thread = std::thread::spawn(move || {
+            let mut gilrs = match Gilrs::new() {
+                Ok(gilrs) => {
+                    let _ = init_sender.send_blocking(Ok(()));
+                    gilrs
+                }
+                Err(e) => {
+                    let _ = init_sender.send_blocking(Err(e.to_string()));
+                    return;
+                }
+            };
+
+            for (id, gamepad) in gilrs.gamepads() {
+                log::debug!("Gamepad {} connected: {}", usize::from(id), gamepad.name());
+            }
+
+            while running_clone.load(Ordering::Relaxed) {
+                let Some(gilrs_event) = gilrs.next_event_blocking(Some(POLL_INTERVAL)) else {
+                    continue;
+                };
+
+                if let Some(event) = convert_event(gilrs_event) {
+                    let _ = window.event_broadcast_sender.try_broadcast(event.clone());
+                    window.dispatch_event(event);
+                }
+            }
+        });
+
+        init_receiver.recv_blocking()
+                     .map_err(|e| PsybeeError::GamepadError(e.to_string()))?
+                     .map_err(PsybeeError::GamepadError)?;
+
+        Ok(Self { running,
+                  thread: Some(thread) })
+    }
+}
+
+impl Drop for GamepadInput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Converts a gilrs event to an Event. Returns None for events that are not
+/// forwarded (e.g. connection events).
+fn convert_event(event: gilrs::Event) -> Option<Event> {
+    // gilrs reports the (wall clock) time the event was emitted, so we correct
+    // for the time the event has spent in the queue (unless the wall clock jumped
+    // so far that this would be before the start of the monotonic clock)
+    let age = SystemTime::now().duration_since(event.time).unwrap_or_default();
+    let now = Timestamp::now();
+    let timestamp = now.checked_sub(age).unwrap_or(now);
+    let gamepad = usize::from(event.id);
+
+    match event.event {
+        EventType::ButtonPressed(button, _) => Some(Event::GamepadButtonPress { timestamp,
+                                                                               gamepad,
+                                                                               gamepad_button: button.into() }),
+        EventType::ButtonReleased(button, _) => Some(Event::GamepadButtonRelease { timestamp,
+                                                                                  gamepad,
+                                                                                  gamepad_button: button.into() }),
+        EventType::AxisChanged(axis, value, _) => Some(Event::GamepadAxisMotion { timestamp,
+                                                                                 gamepad,
+                                                                                 axis: axis.into(),
+                                                                                 value }),
+        EventType::Connected => {
+            log::debug!("Gamepad {} connected", gamepad);
+            None
+        }
+        EventType::Disconnected => {
+            log::debug!("Gamepad {} disconnected", gamepad);
+            None
+        }
+        _ => None,
+    }
+}
+
+impl From<Button> for GamepadButton {
+    fn from(button: Button) -> Self {
+        match button {
+            Button::South => GamepadButton::South,
+            Button::East => GamepadButton::East,
+            Button::North => GamepadButton::North,
+            Button::West => GamepadButton::West,
+            Button::C => GamepadButton::C,
+            Button::Z => GamepadButton::Z,
+            Button::LeftTrigger => GamepadButton::LeftTrigger,
+            Button::LeftTrigger2 => GamepadButton::LeftTrigger2,
+            Button::RightTrigger => GamepadButton::RightTrigger,
+            Button::RightTrigger2 => GamepadButton::RightTrigger2,
+            Button::Select => GamepadButton::Select,
+            Button::Start => GamepadButton::Start,
+            Button::Mode => GamepadButton::Mode,
+            Button::LeftThumb => GamepadButton::LeftThumb,
+            Button::RightThumb => GamepadButton::RightThumb,
+            Button::DPadUp => GamepadButton::DPadUp,
+            Button::DPadDown => GamepadButton::DPadDown,
+            Button::DPadLeft => GamepadButton::DPadLeft,
+            Button::DPadRight => GamepadButton::DPadRight,
+            Button::Unknown => GamepadButton::Unknown,
+        }
+    }
+}
+
+impl From<Axis> for GamepadAxis {
+    fn from(axis: Axis) -> Self {
+        match axis {
+            Axis::LeftStickX => GamepadAxis::LeftStickX,
+            Axis::LeftStickY => GamepadAxis::LeftStickY,
+            Axis::LeftZ => GamepadAxis::LeftZ,
+            Axis::RightStickX => GamepadAxis::RightStickX,
+            Axis::RightStickY => GamepadAxis::RightStickY,
+            Axis::RightZ => GamepadAxis::RightZ,
+            Axis::DPadX => GamepadAxis::DPadX,
+            Axis::DPadY => GamepadAxis::DPadY,
+            Axis::Unknown => GamepadAxis::Unknown,
+        }
+    }
+}
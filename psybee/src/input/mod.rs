@@ -1,8 +1,10 @@
 use std::ops::Deref;
+use std::time::Duration;
 
-use web_time::SystemTime;
 use winit::event as winit_event;
 pub use winit::keyboard::KeyCode as Key;
+pub use winit::keyboard::{Key as LogicalKey, ModifiersState as Modifiers, NamedKey};
+use winit::keyboard::PhysicalKey;
 #[cfg(any(target_os = "windows",
               target_os = "macos",
               target_os = "linux",
@@ -12,11 +14,18 @@ pub use winit::keyboard::KeyCode as Key;
               target_os = "netbsd"))]
 use winit::platform::scancode::PhysicalKeyExtScancode;
 
+use crate::clock::Timestamp;
 use crate::visual::geometry::Size;
 use crate::visual::Window;
 
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub(crate) mod pointer;
+pub mod response;
 pub mod video;
 
+pub use response::{Response, ResponseCollector};
+
 /// A mouse button.
 #[derive(Debug, Clone, PartialEq)]
 pub enum MouseButton {
@@ -34,33 +43,159 @@ pub enum MouseButton {
     Other(u16),
 }
 
+/// A gamepad button. The action buttons are named after their position, as
+/// their labels differ between controllers (e.g. `South` is A on Xbox and
+/// Cross on PlayStation controllers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    /// The bottom action button.
+    South,
+    /// The right action button.
+    East,
+    /// The top action button.
+    North,
+    /// The left action button.
+    West,
+    /// The C button (only on some controllers).
+    C,
+    /// The Z button (only on some controllers).
+    Z,
+    /// The left shoulder button.
+    LeftTrigger,
+    /// The second left shoulder button (or trigger).
+    LeftTrigger2,
+    /// The right shoulder button.
+    RightTrigger,
+    /// The second right shoulder button (or trigger).
+    RightTrigger2,
+    /// The select (or back) button.
+    Select,
+    /// The start button.
+    Start,
+    /// The mode (or home) button.
+    Mode,
+    /// Pressing the left stick.
+    LeftThumb,
+    /// Pressing the right stick.
+    RightThumb,
+    /// Up on the directional pad.
+    DPadUp,
+    /// Down on the directional pad.
+    DPadDown,
+    /// Left on the directional pad.
+    DPadLeft,
+    /// Right on the directional pad.
+    DPadRight,
+    /// A button that could not be identified.
+    Unknown,
+}
+
+/// A gamepad axis. Values range from -1.0 to 1.0, with y pointing up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    /// Horizontal position of the left stick.
+    LeftStickX,
+    /// Vertical position of the left stick.
+    LeftStickY,
+    /// The left analog trigger (only on some controllers).
+    LeftZ,
+    /// Horizontal position of the right stick.
+    RightStickX,
+    /// Vertical position of the right stick.
+    RightStickY,
+    /// The right analog trigger (only on some controllers).
+    RightZ,
+    /// Horizontal position of the directional pad (only on some controllers).
+    DPadX,
+    /// Vertical position of the directional pad (only on some controllers).
+    DPadY,
+    /// An axis that could not be identified.
+    Unknown,
+}
+
+/// A key combination, i.e. a physical key together with the modifier keys that
+/// must be held down (e.g. Ctrl+Shift+Q).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    /// The physical key.
+    pub key: Key,
+    /// The modifier keys. These must match exactly.
+    pub modifiers: Modifiers,
+}
+
+impl KeyCombo {
+    /// Create a new key combination.
+    pub fn new(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, modifiers }
+    }
+
+    /// Returns true if the event is a (non-repeated) press of this key
+    /// combination.
+    pub fn matches(&self, event: &Event) -> bool {
+        matches!(event, Event::KeyPress { key_code: Some(key), modifiers, repeat: false, .. } if *key == self.key && *modifiers == self.modifiers)
+    }
+}
+
+impl Default for KeyCombo {
+    /// The default abort key combination (Escape without modifiers).
+    fn default() -> Self {
+        Self::new(Key::Escape, Modifiers::empty())
+    }
+}
+
+impl From<Key> for KeyCombo {
+    fn from(key: Key) -> Self {
+        Self::new(key, Modifiers::empty())
+    }
+}
+
 #[derive(Debug, Clone, enum_fields::EnumFields, strum::EnumDiscriminants)]
 #[strum_discriminants(name(EventKind))]
 pub enum Event {
     /// A keypress event. This is triggered when a key is pressed.
     KeyPress {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// String representation of the key that was pressed.
         key: String,
-        /// KeyCode of the key that was pressed.
+        /// Scancode of the key that was pressed.
         code: u32,
+        /// Physical key that was pressed, independent of the keyboard layout
+        /// (None if the key could not be identified).
+        key_code: Option<Key>,
+        /// Logical key that was pressed, taking the keyboard layout into
+        /// account.
+        logical_key: LogicalKey,
+        /// Modifier keys that were held down.
+        modifiers: Modifiers,
+        /// True if this event was generated by the key being held down.
+        repeat: bool,
     },
     /// A key release event. This is triggered when a key is released.
     KeyRelease {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// String representation of the key that was released.
         key: String,
-        /// KeyCode of the key that was released.
+        /// Scancode of the key that was released.
         code: u32,
+        /// Physical key that was released, independent of the keyboard layout
+        /// (None if the key could not be identified).
+        key_code: Option<Key>,
+        /// Logical key that was released, taking the keyboard layout into
+        /// account.
+        logical_key: LogicalKey,
+        /// Modifier keys that were held down.
+        modifiers: Modifiers,
+        /// True if this event was generated by the key being held down.
+        repeat: bool,
     },
 
     /// A mouse button press event. This is triggered when a mouse button is
     /// pressed.
     MouseButtonPress {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The button that was pressed.
         button: MouseButton,
         /// The position of the mouse cursor when the button was pressed.
@@ -71,7 +206,7 @@ pub enum Event {
     /// released.
     MouseButtonRelease {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The button that was released.
         button: MouseButton,
         /// The position of the mouse cursor when the button was released.
@@ -80,7 +215,7 @@ pub enum Event {
     /// A touch start event. This is triggered when a touch screen is touched.
     TouchStart {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The position of the touch.
         position: (Size, Size),
         /// The id of the touch (if available).
@@ -89,7 +224,7 @@ pub enum Event {
     /// A touch move event. This is triggered when a touch screen is moved.
     TouchMove {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The position of the touch.
         position: (Size, Size),
         /// The id of the touch (if available).
@@ -99,7 +234,7 @@ pub enum Event {
     /// released.
     TouchEnd {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The position of the touch.
         position: (Size, Size),
         /// The id of the touch (if available).
@@ -109,7 +244,7 @@ pub enum Event {
     /// cancelled.
     TouchCancel {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The position of the touch.
         position: (Size, Size),
         /// The id of the touch (if available).
@@ -118,34 +253,34 @@ pub enum Event {
     /// The window has lost focus.
     FocusGained {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
     },
     /// The window has gained focus.
     FocusLost {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
     },
     /// The mouse cursor was moved.
     CursorMoved {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The position of the cursor.
         position: (Size, Size),
     },
     /// The mouse cursor was entered into the window.
     CursorEntered {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
     },
     /// The mouse cursor was exited from the window.
     CursorExited {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
     },
     /// A pressure-sensitive touchpad was pressed (if available).
     TouchpadPress {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The pressure of the touch.
         pressure: f32,
         /// The level of the touch.
@@ -154,16 +289,107 @@ pub enum Event {
     /// The mouse wheel was scrolled (or the equivalent touchpad gesture).
     MouseWheel {
         /// Timestamp of the event.
-        timestamp: SystemTime,
-        /// The amount of horizontal scrolling.
+        timestamp: Timestamp,
+        /// The amount of horizontal scrolling (in lines, or in pixels for
+        /// devices that scroll smoothly, e.g. touchpads).
         horizontal: f32,
-        /// The amount of vertical scrolling.
+        /// The amount of vertical scrolling (in lines, or in pixels for devices
+        /// that scroll smoothly, e.g. touchpads).
         vertical: f32,
     },
+    /// The state of the modifier keys changed.
+    ModifiersChanged {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+        /// The modifier keys that are now held down.
+        modifiers: Modifiers,
+    },
+    /// A drag started, i.e. the mouse cursor was moved while a mouse button
+    /// was held down.
+    DragStart {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+        /// The button that is held down.
+        button: MouseButton,
+        /// The position of the mouse cursor when the button was pressed.
+        start_position: (Size, Size),
+        /// The current position of the mouse cursor.
+        position: (Size, Size),
+    },
+    /// The mouse cursor was moved during a drag.
+    DragMove {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+        /// The buttons that are held down.
+        buttons: Vec<MouseButton>,
+        /// The position of the mouse cursor when the drag started.
+        start_position: (Size, Size),
+        /// The current position of the mouse cursor.
+        position: (Size, Size),
+        /// The velocity of the mouse cursor in pixels per second.
+        velocity: (f64, f64),
+    },
+    /// A drag ended, i.e. the button that started the drag was released.
+    DragEnd {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+        /// The button that was released.
+        button: MouseButton,
+        /// The position of the mouse cursor when the drag started.
+        start_position: (Size, Size),
+        /// The position of the mouse cursor when the button was released.
+        position: (Size, Size),
+    },
+    /// A gamepad button was pressed (see `gamepad::GamepadInput`).
+    GamepadButtonPress {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+        /// The id of the gamepad.
+        gamepad: usize,
+        /// The button that was pressed.
+        gamepad_button: GamepadButton,
+    },
+    /// A gamepad button was released (see `gamepad::GamepadInput`).
+    GamepadButtonRelease {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+        /// The id of the gamepad.
+        gamepad: usize,
+        /// The button that was released.
+        gamepad_button: GamepadButton,
+    },
+    /// A gamepad stick or analog trigger was moved (see
+    /// `gamepad::GamepadInput`).
+    GamepadAxisMotion {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+        /// The id of the gamepad.
+        gamepad: usize,
+        /// The axis that was moved.
+        axis: GamepadAxis,
+        /// The new value of the axis (-1.0 to 1.0).
+        value: f32,
+    },
+    /// A byte was received from a response box connected to a serial port
+    /// (see `serial::SerialResponseBox`).
+    ResponseBoxInput {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+        /// The name of the serial port.
+        port: String,
+        /// The byte that was received.
+        byte: u8,
+    },
+    /// The abort key combination (see `KeyCombo`) was pressed. If no event
+    /// handler handles this event, the experiment is shut down.
+    AbortRequested {
+        /// Timestamp of the event.
+        timestamp: Timestamp,
+    },
     /// Any other event. The string contains the name of the event.
     Other {
         /// Timestamp of the event.
-        timestamp: SystemTime,
+        timestamp: Timestamp,
         /// The name of the event.
         name: String,
     },
@@ -181,6 +407,20 @@ impl Event {
         matches!(&self, Self::KeyRelease { key: k, .. } if k == key)
     }
 
+    /// Returns true if this element represents a press of the given physical
+    /// key. Unlike `key_pressed()`, this does not depend on the keyboard
+    /// layout.
+    pub fn key_code_pressed(&self, key: Key) -> bool {
+        matches!(&self, Self::KeyPress { key_code: Some(k), .. } if *k == key)
+    }
+
+    /// Returns true if this element represents a release of the given
+    /// physical key. Unlike `key_released()`, this does not depend on the
+    /// keyboard layout.
+    pub fn key_code_released(&self, key: Key) -> bool {
+        matches!(&self, Self::KeyRelease { key_code: Some(k), .. } if *k == key)
+    }
+
     /// Returns true if this element represents a press of the given mouse
     /// button.
     pub fn mouse_button_pressed(&self, button_a: MouseButton) -> bool {
@@ -193,10 +433,35 @@ impl Event {
         matches!(&self, Self::MouseButtonRelease { button, .. } if button_a == *button)
     }
 
+    /// Returns true if this element represents a press of the given gamepad
+    /// button (on any gamepad).
+    pub fn gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        matches!(&self, Self::GamepadButtonPress { gamepad_button, .. } if button == *gamepad_button)
+    }
+
+    /// Returns true if this element represents a release of the given gamepad
+    /// button (on any gamepad).
+    pub fn gamepad_button_released(&self, button: GamepadButton) -> bool {
+        matches!(&self, Self::GamepadButtonRelease { gamepad_button, .. } if button == *gamepad_button)
+    }
+
     /// Returns the kind of this event.
     pub fn kind(&self) -> EventKind {
         self.into()
     }
+
+    /// Returns true if this event is of one of the given kinds and, for key
+    /// events, refers to one of the given keys. Empty lists match any kind or
+    /// key.
+    pub fn matches<S: AsRef<str>>(&self, kinds: &[EventKind], keys: &[S]) -> bool {
+        let kind_matches = kinds.is_empty() || kinds.contains(&self.kind());
+        let key_matches = match self.key() {
+            Some(key) => keys.is_empty() || keys.iter().any(|k| k.as_ref() == key),
+            None => true,
+        };
+
+        kind_matches && key_matches
+    }
 }
 
 // Custom conversion from winit events to InputEvents.
@@ -211,13 +476,13 @@ impl EventTryFrom<winit_event::WindowEvent> for Event {
     type Error = &'static str;
 
     fn try_from_winit(event: winit_event::WindowEvent, window: &Window) -> Result<Self, Self::Error> {
-        let timestamp = SystemTime::now();
+        let timestamp = Timestamp::now();
         let data = match event {
             // match keyboad events
             winit_event::WindowEvent::KeyboardInput { device_id: _, event, .. } => {
                 let key_str = event.logical_key.to_text().unwrap_or_default();
 
-                let key_code = u32::default();
+                let scancode = u32::default();
 
                 #[cfg(any(target_os = "windows",
                           target_os = "macos",
@@ -226,17 +491,47 @@ impl EventTryFrom<winit_event::WindowEvent> for Event {
                           target_os = "dragonfly",
                           target_os = "openbsd",
                           target_os = "netbsd"))]
-                let key_code = event.physical_key.to_scancode().unwrap_or_default();
+                let scancode = event.physical_key.to_scancode().unwrap_or_default();
+
+                let key_code = match event.physical_key {
+                    PhysicalKey::Code(code) => Some(code),
+                    PhysicalKey::Unidentified(_) => None,
+                };
+                let modifiers = window.modifiers();
 
                 match event.state {
                     winit_event::ElementState::Pressed => Event::KeyPress { timestamp: timestamp,
                                                                             key: key_str.to_string(),
-                                                                            code: key_code },
+                                                                            code: scancode,
+                                                                            key_code,
+                                                                            logical_key: event.logical_key,
+                                                                            modifiers,
+                                                                            repeat: event.repeat },
                     winit_event::ElementState::Released => Event::KeyRelease { timestamp: timestamp,
                                                                                key: key_str.to_string(),
-                                                                               code: key_code },
+                                                                               code: scancode,
+                                                                               key_code,
+                                                                               logical_key: event.logical_key,
+                                                                               modifiers,
+                                                                               repeat: event.repeat },
                 }
             }
+            // match modifier events (and keep track of the modifier keys)
+            winit_event::WindowEvent::ModifiersChanged(modifiers) => {
+                *window.modifiers.lock_blocking() = modifiers.state();
+                Event::ModifiersChanged { timestamp: timestamp,
+                                          modifiers: modifiers.state() }
+            }
+            // match mouse wheel events
+            winit_event::WindowEvent::MouseWheel { device_id: _, delta, .. } => {
+                let (horizontal, vertical) = match delta {
+                    winit_event::MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    winit_event::MouseScrollDelta::PixelDelta(position) => (position.x as f32, position.y as f32),
+                };
+                Event::MouseWheel { timestamp: timestamp,
+                                    horizontal,
+                                    vertical }
+            }
             // match mouse button events
             winit_event::WindowEvent::MouseInput { device_id: _, state, button } => {
                 let button = match button {
@@ -332,6 +627,30 @@ impl EventVec {
     pub fn key_released(&self, key: &str) -> bool {
         self.iter().any(|key_event| key_event.key_released(key))
     }
+
+    /// Check if the given KeyEventVec contains a press of the provided
+    /// physical key (convenience method).
+    pub fn key_code_pressed(&self, key: Key) -> bool {
+        self.iter().any(|key_event| key_event.key_code_pressed(key))
+    }
+
+    /// Check if the given KeyEventVec contains a release of the provided
+    /// physical key (convenience method).
+    pub fn key_code_released(&self, key: Key) -> bool {
+        self.iter().any(|key_event| key_event.key_code_released(key))
+    }
+
+    /// Check if the given EventVec contains a press of the provided gamepad
+    /// button (convenience method).
+    pub fn gamepad_button_pressed(&self, button: GamepadButton) -> bool {
+        self.iter().any(|event| event.gamepad_button_pressed(button))
+    }
+
+    /// Check if the given EventVec contains a release of the provided gamepad
+    /// button (convenience method).
+    pub fn gamepad_button_released(&self, button: GamepadButton) -> bool {
+        self.iter().any(|event| event.gamepad_button_released(button))
+    }
 }
 
 // make KeyEventVec behave like a vector of KeyEvents
@@ -358,6 +677,39 @@ impl EventReceiver {
     pub fn flush(&mut self) {
         while let Ok(_) = self.receiver.try_recv() {}
     }
+
+    /// Blocks until an event of one of the given kinds (and, for key events,
+    /// one of the given keys) is received and returns it. Empty lists match
+    /// any kind or key. Events that do not match are discarded. Returns None if
+    /// the timeout elapses first.
+    ///
+    /// Not available on wasm, where the experiment must not block.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait_for<S: AsRef<str>>(&mut self, kinds: &[EventKind], keys: &[S], timeout: Option<Duration>) -> Option<Event> {
+        use futures_lite::future::{block_on, or};
+
+        // the timer keeps running while non-matching events are discarded
+        let mut timer = timeout.map(smol::Timer::after);
+
+        loop {
+            let event = match &mut timer {
+                Some(timer) => {
+                    let timeout = async {
+                        timer.await;
+                        None
+                    };
+                    block_on(or(async { Some(self.receiver.recv().await) }, timeout))?
+                }
+                None => block_on(self.receiver.recv()),
+            };
+
+            match event {
+                Ok(event) if event.matches(kinds, keys) => return Some(event),
+                Ok(_) | Err(async_broadcast::RecvError::Overflowed(_)) => continue,
+                Err(async_broadcast::RecvError::Closed) => return None,
+            }
+        }
+    }
 }
 
 pub(crate) type EventHandlerId = usize;
@@ -379,3 +731,70 @@ pub trait EventHandlingExt {
     /// consumed by any of the handlers.
     fn dispatch_event(&self, event: Event) -> bool;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_press(key: &str) -> Event {
+        Event::KeyPress { timestamp: Timestamp::now(),
+                          key: key.to_string(),
+                          code: 0,
+                          key_code: None,
+                          logical_key: LogicalKey::Character(key.into()),
+                          modifiers: Modifiers::empty(),
+                          repeat: false }
+    }
+
+    fn mouse_press() -> Event {
+        Event::MouseButtonPress { timestamp: Timestamp::now(),
+                                  button: MouseButton::Left,
+                                  position: (Size::Pixels(0.0), Size::Pixels(0.0)) }
+    }
+
+    #[test]
+    fn wait_for_discards_events_that_do_not_match() {
+        let (sender, receiver) = async_broadcast::broadcast(10);
+        let mut receiver = EventReceiver { receiver };
+
+        sender.try_broadcast(mouse_press()).unwrap();
+        sender.try_broadcast(key_press("a")).unwrap();
+        sender.try_broadcast(key_press("j")).unwrap();
+        sender.try_broadcast(key_press("f")).unwrap();
+
+        let event = receiver.wait_for(&[EventKind::KeyPress], &["f", "j"], Some(Duration::from_secs(1)));
+        assert!(event.is_some_and(|event| event.key_pressed("j")));
+
+        // empty lists match any event
+        let event = receiver.wait_for(&[], &[] as &[&str], Some(Duration::from_secs(1)));
+        assert!(event.is_some_and(|event| event.key_pressed("f")));
+    }
+
+    #[test]
+    fn wait_for_returns_none_after_the_timeout() {
+        let (sender, receiver) = async_broadcast::broadcast(10);
+        let mut receiver = EventReceiver { receiver };
+
+        let start = web_time::Instant::now();
+        assert!(receiver.wait_for(&[EventKind::KeyPress], &[] as &[&str], Some(Duration::from_millis(50))).is_none());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // events that do not match do not extend the timeout
+        let feeder = std::thread::spawn(move || {
+            for _ in 0..20 {
+                let _ = sender.try_broadcast(mouse_press());
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            sender
+        });
+
+        let start = web_time::Instant::now();
+        assert!(receiver.wait_for(&[EventKind::KeyPress], &[] as &[&str], Some(Duration::from_millis(50))).is_none());
+        assert!(start.elapsed() < Duration::from_millis(150));
+
+        // the receiver is closed once the sender is dropped
+        drop(feeder.join().unwrap());
+        receiver.flush();
+        assert!(receiver.wait_for(&[], &[] as &[&str], None).is_none());
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Pointer tracking. Keeps track of the pressed mouse buttons and the velocity
+//! of the mouse cursor, and turns button presses followed by cursor movements
+//! into drag events (`Event::DragStart`, `Event::DragMove` and
+//! `Event::DragEnd`).
+
+use winit::event as winit_event;
+
+use super::{Event, MouseButton};
+use crate::clock::Timestamp;
+use crate::visual::geometry::Size;
+
+/// Distance (in pixels) the cursor has to move while a button is held down
+/// before a drag starts. This prevents small movements during a click from
+/// being reported as drags.
+const DRAG_THRESHOLD_PX: f64 = 3.0;
+
+/// An ongoing drag.
+#[derive(Debug, Clone)]
+struct Drag {
+    /// The button that started the drag.
+    button: MouseButton,
+    /// The position of the cursor when the button was pressed.
+    start_position: (Size, Size),
+}
+
+/// A button press that might start a drag.
+#[derive(Debug, Clone)]
+struct Press {
+    /// The position of the cursor as reported in the press event.
+    position: (Size, Size),
+    /// The position of the cursor in pixels (y pointing up), or None if the
+    /// cursor has not moved since the pointer entered the window.
+    position_px: Option<(f64, f64)>,
+}
+
+/// The state of the pointer (mouse cursor) of a window.
+#[derive(Debug, Default)]
+pub(crate) struct PointerState {
+    /// The currently pressed buttons, in the order they were pressed.
+    buttons: Vec<MouseButton>,
+    /// The press of the first button that is held down.
+    press: Option<Press>,
+    /// The ongoing drag, if any.
+    drag: Option<Drag>,
+    /// The time and position (in pixels, y pointing up) of the last cursor
+    /// movement.
+    last_movement: Option<(Timestamp, (f64, f64))>,
+    /// The velocity of the cursor in pixels per second.
+    velocity: (f64, f64),
+}
+
+impl PointerState {
+    /// Returns the currently pressed mouse buttons.
+    pub(crate) fn buttons(&self) -> Vec<MouseButton> {
+        self.buttons.clone()
+    }
+
+    /// Returns the velocity of the cursor in pixels per second (y pointing
+    /// up), measured between the last two cursor movements.
+    pub(crate) fn velocity(&self) -> (f64, f64) {
+        self.velocity
+    }
+
+    /// Update the state with a winit event and the event it was converted to.
+    /// Returns a drag event if the update started, continued or ended a drag.
+    pub(crate) fn update(&mut self, winit_event: &winit_event::WindowEvent, event: &Event) -> Option<Event> {
+        match (winit_event, event) {
+            (winit_event::WindowEvent::CursorMoved { position: raw, .. }, Event::CursorMoved { timestamp, position }) => {
+                let timestamp = *timestamp;
+                let position_px = (raw.x, -raw.y);
+
+                if let Some((last_time, last_px)) = self.last_movement {
+                    let dt = timestamp.secs_since(last_time);
+                    if dt > 0.0 {
+                        self.velocity = ((position_px.0 - last_px.0) / dt, (position_px.1 - last_px.1) / dt);
+                    }
+                }
+                self.last_movement = Some((timestamp, position_px));
+
+                if let Some(drag) = &self.drag {
+                    return Some(Event::DragMove { timestamp,
+                                                  buttons: self.buttons.clone(),
+                                                  start_position: drag.start_position.clone(),
+                                                  position: position.clone(),
+                                                  velocity: self.velocity });
+                }
+
+                // start a drag once the cursor has moved far enough from where the button was pressed
+                let press = self.press.as_mut()?;
+                let Some(start_px) = press.position_px else {
+                    // the button was pressed before the cursor position was known, so measure
+                    // the distance from the first known position instead
+                    press.position_px = Some(position_px);
+                    return None;
+                };
+                let distance = (position_px.0 - start_px.0).hypot(position_px.1 - start_px.1);
+                if distance < DRAG_THRESHOLD_PX {
+                    return None;
+                }
+
+                let drag = Drag { button: self.buttons.first()?.clone(),
+                                  start_position: press.position.clone() };
+                let event = Event::DragStart { timestamp,
+                                               button: drag.button.clone(),
+                                               start_position: drag.start_position.clone(),
+                                               position: position.clone() };
+                self.drag = Some(drag);
+                Some(event)
+            }
+            (_, Event::MouseButtonPress { button, position, .. }) => {
+                if self.buttons.is_empty() {
+                    self.press = Some(Press { position: position.clone(),
+                                              position_px: self.last_movement.map(|(_, px)| px) });
+                }
+                if !self.buttons.contains(button) {
+                    self.buttons.push(button.clone());
+                }
+                None
+            }
+            (_, Event::MouseButtonRelease { timestamp, button, position }) => {
+                self.buttons.retain(|b| b != button);
+                if self.buttons.is_empty() {
+                    self.press = None;
+                }
+
+                // only the button that started the drag ends it
+                if self.drag.as_ref().is_some_and(|drag| drag.button == *button) {
+                    let drag = self.drag.take()?;
+                    self.press = None;
+                    return Some(Event::DragEnd { timestamp: *timestamp,
+                                                 button: drag.button,
+                                                 start_position: drag.start_position,
+                                                 position: position.clone() });
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{DeviceId, ElementState, WindowEvent};
+
+    use super::*;
+
+    fn position(x: f64, y: f64) -> (Size, Size) {
+        (Size::Pixels(x), Size::Pixels(-y))
+    }
+
+    /// Moves the cursor to the given (winit) position.
+    fn move_to(pointer: &mut PointerState, x: f64, y: f64) -> Option<Event> {
+        let winit_event = WindowEvent::CursorMoved { device_id: unsafe { DeviceId::dummy() },
+                                                     position: PhysicalPosition::new(x, y) };
+        let event = Event::CursorMoved { timestamp: Timestamp::now(),
+                                         position: position(x, y) };
+        pointer.update(&winit_event, &event)
+    }
+
+    fn press(pointer: &mut PointerState, button: MouseButton, x: f64, y: f64) -> Option<Event> {
+        let winit_event = WindowEvent::MouseInput { device_id: unsafe { DeviceId::dummy() },
+                                                    state: ElementState::Pressed,
+                                                    button: winit_event::MouseButton::Left };
+        let event = Event::MouseButtonPress { timestamp: Timestamp::now(),
+                                              button,
+                                              position: position(x, y) };
+        pointer.update(&winit_event, &event)
+    }
+
+    fn release(pointer: &mut PointerState, button: MouseButton, x: f64, y: f64) -> Option<Event> {
+        let winit_event = WindowEvent::MouseInput { device_id: unsafe { DeviceId::dummy() },
+                                                    state: ElementState::Released,
+                                                    button: winit_event::MouseButton::Left };
+        let event = Event::MouseButtonRelease { timestamp: Timestamp::now(),
+                                                button,
+                                                position: position(x, y) };
+        pointer.update(&winit_event, &event)
+    }
+
+    #[test]
+    fn drags_start_after_the_threshold_and_end_on_release() {
+        let mut pointer = PointerState::default();
+
+        assert!(move_to(&mut pointer, 10.0, 10.0).is_none());
+        assert!(press(&mut pointer, MouseButton::Left, 10.0, 10.0).is_none());
+        assert_eq!(pointer.buttons(), vec![MouseButton::Left]);
+
+        // small movements during a click are not drags
+        assert!(move_to(&mut pointer, 11.0, 11.0).is_none());
+
+        let event = move_to(&mut pointer, 20.0, 10.0);
+        assert!(matches!(event, Some(Event::DragStart { button: MouseButton::Left, .. })));
+
+        let event = move_to(&mut pointer, 30.0, 10.0);
+        assert!(matches!(&event, Some(Event::DragMove { buttons, .. }) if *buttons == vec![MouseButton::Left]));
+
+        let event = release(&mut pointer, MouseButton::Left, 30.0, 10.0);
+        assert!(matches!(event, Some(Event::DragEnd { button: MouseButton::Left, .. })));
+        assert!(pointer.buttons().is_empty());
+
+        // moving without a pressed button does not drag
+        assert!(move_to(&mut pointer, 100.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn only_the_first_button_ends_a_drag() {
+        let mut pointer = PointerState::default();
+
+        move_to(&mut pointer, 0.0, 0.0);
+        press(&mut pointer, MouseButton::Left, 0.0, 0.0);
+        press(&mut pointer, MouseButton::Right, 0.0, 0.0);
+        assert!(move_to(&mut pointer, 50.0, 0.0).is_some());
+
+        assert!(release(&mut pointer, MouseButton::Right, 50.0, 0.0).is_none());
+        assert!(matches!(move_to(&mut pointer, 60.0, 0.0), Some(Event::DragMove { .. })));
+        assert!(matches!(release(&mut pointer, MouseButton::Left, 60.0, 0.0), Some(Event::DragEnd { .. })));
+    }
+
+    #[test]
+    fn presses_before_the_cursor_position_is_known_do_not_start_a_drag_immediately() {
+        let mut pointer = PointerState::default();
+
+        // the cursor has not moved yet, so its position is unknown (and not the origin)
+        press(&mut pointer, MouseButton::Left, 200.0, 200.0);
+        assert!(move_to(&mut pointer, 200.0, 200.0).is_none());
+        assert!(move_to(&mut pointer, 201.0, 200.0).is_none());
+
+        // the distance is measured from the first known position
+        assert!(matches!(move_to(&mut pointer, 210.0, 200.0), Some(Event::DragStart { .. })));
+    }
+
+    #[test]
+    fn velocity_is_measured_between_movements() {
+        let mut pointer = PointerState::default();
+
+        move_to(&mut pointer, 0.0, 0.0);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        move_to(&mut pointer, 10.0, 10.0);
+
+        // y points up, i.e. moving down the screen has a negative velocity
+        let (vx, vy) = pointer.velocity();
+        assert!(vx > 0.0 && vx <= 1000.0, "horizontal velocity was {} px/s", vx);
+        assert!((-1000.0..0.0).contains(&vy), "vertical velocity was {} px/s", vy);
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Response collection. A `ResponseCollector` waits for the first event that
+//! matches a set of event kinds and keys and measures the reaction time from
+//! the onset of a presented frame.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # fn run(window: &psybee::visual::Window) {
+//! use std::time::Duration;
+//!
+//! use psybee::input::{EventKind, ResponseCollector};
+//!
+//! // create the collector before presenting the stimulus so that no response is lost
+//! let mut collector = ResponseCollector::new(window, vec![EventKind::KeyPress], vec!["f".to_string(), "j".to_string()]);
+//!
+//! let frame = window.get_frame();
+//! let onset = window.present(frame);
+//!
+//! if let Some(response) = collector.collect(&onset, Some(Duration::from_secs(2))) {
+//!     println!("{:?} after {:?}", response.event.key(), response.reaction_time);
+//! }
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use super::{Event, EventKind, EventReceiver};
+use crate::clock::Timestamp;
+use crate::visual::timing::PresentationInfo;
+use crate::visual::Window;
+
+/// A response and its reaction time.
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// The event that was recorded as the response.
+    pub event: Event,
+    /// Time between the onset of the frame and the event.
+    pub reaction_time: Duration,
+}
+
+/// Collects responses and measures reaction times relative to the onset of a
+/// presented frame.
+#[derive(Debug)]
+pub struct ResponseCollector {
+    receiver: EventReceiver,
+    kinds: Vec<EventKind>,
+    keys: Vec<String>,
+}
+
+impl ResponseCollector {
+    /// Create a new response collector for the given window. Only events of
+    /// the given kinds are considered responses, and key events only if they
+    /// refer to one of the given keys. Empty lists match any kind or key.
+    ///
+    /// Only events that occur after the collector was created can be
+    /// collected.
+    pub fn new(window: &Window, kinds: Vec<EventKind>, keys: Vec<String>) -> Self {
+        Self { receiver: window.create_event_receiver(),
+               kinds,
+               keys }
+    }
+
+    /// Blocks until the first matching event after the onset of the given
+    /// frame and returns it together with the reaction time. Events before the
+    /// onset (e.g. anticipations) are discarded. Returns None if no response is
+    /// given within `timeout` after the onset.
+    ///
+    /// The onset is the flip time of the frame if available, and its submit
+    /// time otherwise (see `PresentationInfo::onset()`).
+    ///
+    /// Not available on wasm, use `try_collect()` instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn collect(&mut self, onset: &PresentationInfo, timeout: Option<Duration>) -> Option<Response> {
+        let onset = onset.onset();
+        let deadline = timeout.map(|timeout| onset + timeout);
+
+        loop {
+            let remaining = deadline.map(|deadline| deadline.duration_since(Timestamp::now()));
+            let event = self.receiver.wait_for(&self.kinds, &self.keys, remaining)?;
+
+            if let Some(response) = response_after(event, onset) {
+                // events after the deadline might still be buffered if we checked too late
+                if deadline.is_some_and(|deadline| *response.event.timestamp() > deadline) {
+                    return None;
+                }
+                return Some(response);
+            }
+        }
+    }
+
+    /// Returns the first matching event after the onset of the given frame
+    /// that has been received so far, without blocking. Useful to check for
+    /// responses while presenting further frames.
+    pub fn try_collect(&mut self, onset: &PresentationInfo) -> Option<Response> {
+        let onset = onset.onset();
+
+        self.receiver
+            .poll()
+            .iter()
+            .filter(|event| event.matches(&self.kinds, &self.keys))
+            .find_map(|event| response_after(event.clone(), onset))
+    }
+
+    /// Discards all events that have been received so far.
+    pub fn flush(&mut self) {
+        self.receiver.flush();
+    }
+}
+
+/// Returns the response if the event occurred after the onset.
+fn response_after(event: Event, onset: Timestamp) -> Option<Response> {
+    let time = *event.timestamp();
+
+    (time >= onset).then(|| Response { reaction_time: time.duration_since(onset),
+                                       event })
+}
@@ -1,11 +1,9 @@
-use std::collections::HashMap;
 use std::pin::Pin;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use async_channel::{bounded, Receiver, Sender};
 use async_lock::{Mutex, RwLock};
-use atomic_float::AtomicF64;
 use derive_debug::Dbg;
 use futures_lite::future::block_on;
 use futures_lite::Future;
@@ -20,13 +18,23 @@ use winit::event::{Event as WinitEvent, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoopBuilder, EventLoopWindowTarget};
 use winit::monitor::VideoMode;
 
-use crate::input::{Event, EventHandlingExt, EventKind, EventTryFrom};
-use crate::visual::color::ColorFormat;
+use crate::clock::Clock;
+use crate::input::{Event, EventHandlingExt, EventTryFrom, KeyCombo};
 
 pub mod audio;
+pub mod clock;
 pub mod errors;
 pub mod input;
+#[cfg(feature = "lsl")]
+pub mod lsl;
+pub mod markers;
 pub mod options;
+pub mod psychometric;
+#[cfg(feature = "serial")]
+pub mod serial;
+pub mod staircase;
+pub mod trials;
+pub mod triggers;
 pub mod utils;
 pub mod visual;
 
@@ -36,8 +44,11 @@ use winit::event_loop::EventLoop;
 
 // the prelude
 pub mod prelude {
+    pub use crate::clock::{Clock, Timestamp};
     pub use crate::errors::PsybeeError;
     pub use crate::input::{EventReceiver, Key};
+    pub use crate::markers::UdpMarkerSender;
+    pub use crate::trials::{TrialHandler, TrialOptions, TrialOrder};
     pub use crate::utils::{sleep_secs, BIDSEventLogger};
     pub use crate::visual::color;
     pub use crate::visual::geometry::{Circle, Rectangle, Size, Transformation2D};
@@ -53,7 +64,7 @@ use std::thread;
 #[cfg(target_arch = "wasm32")]
 use wasm_thread as thread;
 
-use crate::visual::window::{render_task, Frame, InternalWindowState, Window};
+use crate::visual::window::{render_task, InternalWindowState, Window};
 
 #[cfg(target_arch = "wasm32")]
 pub fn web_window() -> web_sys::Window {
@@ -173,6 +184,46 @@ pub enum PsyEventLoopEvent {
     RunOnMainThread(#[dbg(placeholder = "...")] Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>),
 }
 
+/// A function that is called when the experiment shuts down (see
+/// `ExperimentManager::add_shutdown_hook`).
+pub type ShutdownHook = Box<dyn FnOnce() + Send>;
+
+/// How long the event loop waits for the experiment to finish after it was
+/// aborted, before the shutdown hooks are called anyway.
+const ABORT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Functions that are called (in reverse order of registration) when the
+/// experiment shuts down. Clones share the same hooks, and each hook is called
+/// at most once.
+#[derive(Clone, Default)]
+pub(crate) struct ShutdownHooks(Arc<Mutex<Vec<ShutdownHook>>>);
+
+impl ShutdownHooks {
+    /// Register a new hook.
+    pub(crate) fn add(&self, hook: ShutdownHook) {
+        self.0.lock_blocking().push(hook);
+    }
+
+    /// Call and remove all registered hooks.
+    pub(crate) fn run(&self) {
+        let hooks = std::mem::take(&mut *self.0.lock_blocking());
+
+        if !hooks.is_empty() {
+            log::debug!("Running {} shutdown hook(s)", hooks.len());
+        }
+
+        for hook in hooks.into_iter().rev() {
+            hook();
+        }
+    }
+}
+
+impl std::fmt::Debug for ShutdownHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ShutdownHooks({})", self.0.lock_blocking().len())
+    }
+}
+
 /// The GPUState struct holds the state of the wgpu device and queue. It is used
 /// to create new windows.
 #[derive(Debug)]
@@ -183,10 +234,51 @@ pub struct GPUState {
     pub queue: wgpu::Queue,
 }
 
+impl GPUState {
+    /// Create a new GPU state. If `force_fallback_adapter` is true, wgpu will
+    /// select a software (fallback) adapter, which is useful for rendering on
+    /// machines without a GPU, e.g. in CI or on compute nodes.
+    pub async fn new(force_fallback_adapter: bool) -> Self {
+        // this is where we would chose a specific backend
+        let backend = wgpu::Backends::all();
+
+        #[cfg(target_os = "windows")]
+        let backend = wgpu::Backends::VULKAN;
+
+        let instance_desc = wgpu::InstanceDescriptor { backends: backend,
+                                                       // use defaults for the rest
+                                                       ..Default::default() };
+
+        let instance = wgpu::Instance::new(instance_desc);
+
+        // request an adapter
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter,
+                compatible_surface: None, // idealy we would use the surface here, but we don't have it yet
+            })
+                              .await
+                              .expect("Failed to find an suitable graphics adapter. This is likely a bug, please report it.");
+
+        log::debug!("Selected graphics adapter: {:?}", adapter.get_info());
+
+        // Create the logical device and command queue
+        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor { label: None,
+                                                                               required_features: wgpu::Features::empty(),
+                                                                               // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
+                                                                               required_limits: wgpu::Limits::default().using_resolution(adapter.limits()) },
+                                                     None)
+                                     .await
+                                     .expect("Failed to create device. This is likely a bug, please report it.");
+
+        Self { instance, adapter, device, queue }
+    }
+}
+
 /// The MainLoop is the root element of the psybee library.
 #[derive(Debug)]
 pub struct MainLoop {
-    /// The winit event loop
+    /// The winit event loop (None when running headless)
     pub(crate) event_loop: Option<EventLoop<PsyEventLoopEvent>>,
     /// Channel for sending a future to the render task. The future will be
     /// executed on the render thread.
@@ -198,14 +290,25 @@ pub struct MainLoop {
     pub(crate) windows: Vec<Window>,
     /// The current GPU state
     pub(crate) gpu_state: Arc<RwLock<GPUState>>,
+    /// The experiment clock
+    pub(crate) clock: Clock,
+    /// The key combination that aborts the experiment (None to disable)
+    pub(crate) abort_key: Arc<Mutex<Option<KeyCombo>>>,
+    /// Functions to call when the experiment shuts down
+    pub(crate) shutdown_hooks: ShutdownHooks,
 }
 
 /// The ExperimentManager is available to the user in the experiment function.
 #[derive(Debug)]
 pub struct ExperimentManager {
-    event_loop_proxy: winit::event_loop::EventLoopProxy<PsyEventLoopEvent>,
+    /// Proxy to the winit event loop (None when running headless).
+    event_loop_proxy: Option<winit::event_loop::EventLoopProxy<PsyEventLoopEvent>>,
     available_monitors: Vec<Monitor>,
     render_taks_sender: Sender<RenderThreadChannelPayload>,
+    gpu_state: Arc<RwLock<GPUState>>,
+    clock: Clock,
+    abort_key: Arc<Mutex<Option<KeyCombo>>>,
+    shutdown_hooks: ShutdownHooks,
 }
 
 impl ExperimentManager {
@@ -217,7 +320,7 @@ impl ExperimentManager {
         let user_event = PsyEventLoopEvent::PromptEvent(message.to_string(), sender);
 
         // send event
-        self.event_loop_proxy
+        self.event_loop_proxy()
             .send_event(user_event)
             .expect("Failed to send event to event loop. This is likely a bug, please report it.");
 
@@ -236,7 +339,7 @@ impl ExperimentManager {
         let user_event = PsyEventLoopEvent::CreateNewWindowEvent(window_options.clone(), sender);
 
         // send event
-        self.event_loop_proxy.send_event(user_event).expect("Failed to send event to event loop.");
+        self.event_loop_proxy().send_event(user_event).expect("Failed to send event to event loop.");
         log::debug!("Requested new window, waiting for response");
 
         // wait for response
@@ -246,6 +349,66 @@ impl ExperimentManager {
         return window;
     }
 
+    /// Create a new offscreen window with the given width and height in pixels.
+    /// Frames presented to this window are rendered into an offscreen texture
+    /// instead of a window on the screen. This does not require a winit event
+    /// loop and can be used when running headless (see
+    /// `MainLoop::new_headless`).
+    pub fn create_offscreen_window(&self, width: u32, height: u32) -> Window {
+        let gpu_state = self.gpu_state.read_blocking();
+        let device = &gpu_state.device;
+
+        let texture_format = TextureFormat::Bgra8Unorm;
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC;
+
+        let offscreen_texture = device.create_texture(&wgpu::TextureDescriptor { label: Some("offscreen_texture"),
+                                                                                  size: wgpu::Extent3d { width,
+                                                                                                         height,
+                                                                                                         depth_or_array_layers: 1 },
+                                                                                  mip_level_count: 1,
+                                                                                  sample_count: 1,
+                                                                                  dimension: wgpu::TextureDimension::D2,
+                                                                                  format: texture_format,
+                                                                                  usage,
+                                                                                  view_formats: &[texture_format] });
+
+        // the surface configuration is not used to configure a surface here, but the
+        // stimuli rely on it to know about the size and format of the render target
+        let config = wgpu::SurfaceConfiguration { usage,
+                                                  format: texture_format,
+                                                  width,
+                                                  height,
+                                                  present_mode: wgpu::PresentMode::Fifo,
+                                                  alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+                                                  view_formats: vec![texture_format],
+                                                  desired_maximum_frame_latency: 1 };
+
+        drop(gpu_state);
+
+        let window_state = InternalWindowState { window: None,
+                                                 surface: None,
+                                                 config,
+                                                 offscreen_texture: Some(offscreen_texture),
+                                                 next_surface_texture: Mutex::new(None) };
+
+        let window = Window::new(window_state, self.gpu_state.clone(), self.render_taks_sender.clone(), self.clock.clone());
+
+        // start renderer for window
+        {
+            let win_handle = window.clone();
+            #[cfg(target_arch = "wasm32")]
+            spawn_async_task(render_task(win_handle));
+            #[cfg(not(target_arch = "wasm32"))]
+            thread::spawn(move || {
+                smol::block_on(render_task(win_handle));
+            });
+        }
+
+        log::debug!("New offscreen window successfully created");
+
+        return window;
+    }
+
     /// Create a default window. This is a convenience function that creates a
     /// window with the default options.
     pub fn create_default_window(&self) -> Window {
@@ -267,52 +430,88 @@ impl ExperimentManager {
     pub fn get_available_monitors(&self) -> Vec<Monitor> {
         self.available_monitors.clone()
     }
+
+    /// Returns the experiment clock. Its zero is the start of the session, and
+    /// it is used to timestamp input events and frames (see `Timestamp`).
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Set the key combination that aborts the experiment, or None to disable
+    /// aborting with the keyboard. Defaults to Escape (see `KeyCombo`).
+    ///
+    /// When the key combination is pressed, an `Event::AbortRequested` is
+    /// dispatched to the window. If no event handler handles it, the event
+    /// loop exits. The experiment then has two seconds to return (e.g. after
+    /// receiving the `Event::AbortRequested` from an `EventReceiver`) before
+    /// the shutdown hooks are called.
+    pub fn set_abort_key(&self, abort_key: Option<KeyCombo>) {
+        *self.abort_key.lock_blocking() = abort_key;
+    }
+
+    /// Register a function that is called when the experiment shuts down,
+    /// e.g. to flush log files or to close audio and video pipelines. Hooks
+    /// are called in reverse order of registration when the experiment
+    /// function returns, fails, or is aborted.
+    pub fn add_shutdown_hook<F>(&self, hook: F)
+        where F: FnOnce() + Send + 'static
+    {
+        self.shutdown_hooks.add(Box::new(hook));
+    }
+
+    /// Returns the event loop proxy. Panics when running headless, as there is
+    /// no event loop to send events to.
+    fn event_loop_proxy(&self) -> &winit::event_loop::EventLoopProxy<PsyEventLoopEvent> {
+        self.event_loop_proxy
+            .as_ref()
+            .expect("No event loop available. When running headless, only offscreen windows can be created.")
+    }
 }
 
 impl MainLoop {
     pub async fn new() -> Self {
+        // every session gets its own experiment clock, starting now
+        let clock = Clock::new();
+
         // create channel for sending tasks to the render thread
         let (render_task_sender, render_task_receiver) = bounded(100);
         let event_loop = EventLoopBuilder::<PsyEventLoopEvent>::with_user_event().build()
                                                                                  .expect("Failed to create event loop. This is likely a bug, please report it.");
 
-        // this is where we would chose a specific backend
-        let backend = wgpu::Backends::all();
-
-        #[cfg(target_os = "windows")]
-        let backend = wgpu::Backends::VULKAN;
+        let gpu_state = GPUState::new(false).await;
 
-        let instance_desc = wgpu::InstanceDescriptor { backends: backend,
-                                                       // use defaults for the rest
-                                                       ..Default::default() };
-
-        let instance = wgpu::Instance::new(instance_desc);
+        Self { event_loop: Some(event_loop),
+               render_thread_channel_sender: render_task_sender,
+               render_thread_channel_receiver: render_task_receiver,
+               windows: vec![],
+               gpu_state: Arc::new(RwLock::new(gpu_state)),
+               clock,
+               abort_key: Arc::new(Mutex::new(Some(KeyCombo::default()))),
+               shutdown_hooks: ShutdownHooks::default() }
+    }
 
-        // request an adapter
-        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: None, // idealy we would use the surface here, but we don't have it yet
-            })
-                              .await
-                              .expect("Failed to find an suitable graphics adapter. This is likely a bug, please report it.");
+    /// Create a new MainLoop that does not require a display. No winit event
+    /// loop is created and the software (fallback) adapter is used, so only
+    /// offscreen windows (see `ExperimentManager::create_offscreen_window`) can
+    /// be created. This is useful for running experiments in CI or on compute
+    /// nodes.
+    pub async fn new_headless() -> Self {
+        // every session gets its own experiment clock, starting now
+        let clock = Clock::new();
 
-        log::debug!("Selected graphics adapter: {:?}", adapter.get_info());
+        // create channel for sending tasks to the render thread
+        let (render_task_sender, render_task_receiver) = bounded(100);
 
-        // Create the logical device and command queue
-        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor { label: None,
-                                                                               required_features: wgpu::Features::empty(),
-                                                                               // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
-                                                                               required_limits: wgpu::Limits::default().using_resolution(adapter.limits()) },
-                                                     None)
-                                     .await
-                                     .expect("Failed to create device. This is likely a bug, please report it.");
+        let gpu_state = GPUState::new(true).await;
 
-        Self { event_loop: Some(event_loop),
+        Self { event_loop: None,
                render_thread_channel_sender: render_task_sender,
                render_thread_channel_receiver: render_task_receiver,
                windows: vec![],
-               gpu_state: Arc::new(RwLock::new(GPUState { instance, adapter, device, queue })) }
+               gpu_state: Arc::new(RwLock::new(gpu_state)),
+               clock,
+               abort_key: Arc::new(Mutex::new(Some(KeyCombo::default()))),
+               shutdown_hooks: ShutdownHooks::default() }
     }
 
     /// Create a new window with the given options.
@@ -437,7 +636,15 @@ impl MainLoop {
         let swapchain_format = TextureFormat::Bgra8Unorm;
         let swapchain_view_format = vec![TextureFormat::Bgra8Unorm];
 
-        let config = wgpu::SurfaceConfiguration { usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        // we need to be able to copy from the swapchain texture to capture frames
+        let usage = if swapchain_capabilities.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC
+        } else {
+            log::warn!("The surface does not support copying from the swapchain texture. Frame capture will not be available for this window.");
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        };
+
+        let config = wgpu::SurfaceConfiguration { usage,
                                                   format: swapchain_format,
                                                   width: size.width,
                                                   height: size.height,
@@ -466,52 +673,15 @@ impl MainLoop {
             surface.as_hal::<wgpu::core::api::Dx12, _, _>(hal_surface_callback).unwrap();
         }
 
-        // create channel for frame submission
-        let (frame_sender, frame_receiver): (Sender<Arc<Mutex<Frame>>>, Receiver<Arc<Mutex<Frame>>>) = bounded(1);
-
-        let (frame_ok_sender, frame_ok_receiver): (Sender<bool>, Receiver<bool>) = bounded(1);
-
         // create a pwindow
-        let window_state = InternalWindowState { window: winit_window.clone(),
-                                                 surface,
-                                                 config };
-
-        // create channel for physical input
-        let (mut event_broadcast_sender, physical_input_receiver) = async_broadcast::broadcast(10_000);
-        event_broadcast_sender.set_overflow(true);
-        // deactivate the receiver
-        let event_broadcast_receiver = physical_input_receiver.deactivate();
+        let window_state = InternalWindowState { window: Some(winit_window.clone()),
+                                                 surface: Some(surface),
+                                                 config,
+                                                 offscreen_texture: None,
+                                                 next_surface_texture: Mutex::new(None) };
 
         // create handle
-        let window = Window { state: Arc::new(RwLock::new(window_state)),
-                              gpu_state: self.gpu_state.clone(),
-                              mouse_position: Arc::new(Mutex::new(None)),
-                              mouse_cursor_visible: Arc::new(AtomicBool::new(true)),
-                              event_broadcast_receiver,
-                              event_broadcast_sender,
-                              frame_channel_sender: frame_sender,
-                              frame_channel_receiver: frame_receiver,
-                              frame_consumed_channel_sender: frame_ok_sender,
-                              frame_consumed_channel_receiver: frame_ok_receiver,
-                              physical_width: Arc::new(AtomicF64::new(300.0)),
-                              viewing_distance: Arc::new(AtomicF64::new(57.0)),
-                              color_format: ColorFormat::SRGBA8,
-                              width_px: Arc::new(AtomicU32::new(300)),
-                              height_px: Arc::new(AtomicU32::new(300)),
-                              render_task_sender: self.render_thread_channel_sender.clone(),
-                              stimuli: Arc::new(Mutex::new(vec![])),
-                              event_handlers: Arc::new(RwLock::new(HashMap::new())),
-                              options: Arc::new(Mutex::new(options::GlobalOptions::default())) };
-
-        let win_clone = window.clone();
-        // add a default event handler for mouse move events, which updates the mouse
-        // position
-        window.add_event_handler(EventKind::CursorMoved, move |event| {
-                  if let Some(pos) = event.position() {
-                      win_clone.mouse_position.lock_blocking().replace(pos.clone());
-                  };
-                  false
-              });
+        let window = Window::new(window_state, self.gpu_state.clone(), self.render_thread_channel_sender.clone(), self.clock.clone());
 
         return window;
     }
@@ -562,7 +732,10 @@ impl MainLoop {
 
     pub fn get_available_monitors(&mut self) -> Vec<Monitor> {
         let mut monitors = vec![];
-        let event_loop = self.event_loop.as_ref().unwrap();
+        let Some(event_loop) = self.event_loop.as_ref() else {
+            // no monitors are available when running headless
+            return monitors;
+        };
         for (i, handle) in event_loop.available_monitors().enumerate() {
             monitors.push(Monitor { name: handle.name().unwrap_or(format!("Unnamed monitor {}", i)),
                                     handle: handle });
@@ -571,7 +744,9 @@ impl MainLoop {
     }
 
     /// Starts the experiment. This will block until the experiment is finished
-    /// and exit the program afterwards.
+    /// and exit the program afterwards. When running headless (see
+    /// `MainLoop::new_headless()`), this returns once the experiment is
+    /// finished instead, and panics if the experiment returned an error.
     ///
     /// # Arguments
     ///
@@ -581,7 +756,28 @@ impl MainLoop {
     pub fn run_experiment<F>(&mut self, experiment_fn: F) -> ()
         where F: FnOnce(ExperimentManager) -> Result<(), errors::PsybeeError> + 'static + Send
     {
-        let event_loop = self.event_loop.take().unwrap();
+        // when running headless, there is no event loop to drive, so we simply run the
+        // experiment on the current thread
+        let Some(event_loop) = self.event_loop.take() else {
+            let wm = ExperimentManager { event_loop_proxy: None,
+                                         render_taks_sender: self.render_thread_channel_sender.clone(),
+                                         available_monitors: vec![],
+                                         gpu_state: self.gpu_state.clone(),
+                                         clock: self.clock.clone(),
+                                         abort_key: self.abort_key.clone(),
+                                         shutdown_hooks: self.shutdown_hooks.clone() };
+
+            let res = experiment_fn(wm);
+            self.shutdown_hooks.run();
+
+            // there is no program to quit when running headless (e.g. in tests), so we
+            // panic instead of exiting the process
+            if let Err(e) = res {
+                panic!("Experiment failed with {:?}: {:}", e, e);
+            }
+
+            return;
+        };
 
         #[cfg(not(target_arch = "wasm32"))]
         {
@@ -619,9 +815,13 @@ impl MainLoop {
                                                                     handle: monitor })
                                            .collect();
 
-        let wm = ExperimentManager { event_loop_proxy: event_loop.create_proxy(),
+        let wm = ExperimentManager { event_loop_proxy: Some(event_loop.create_proxy()),
                                      render_taks_sender: self.render_thread_channel_sender.clone(),
-                                     available_monitors: available_monitors };
+                                     available_monitors: available_monitors,
+                                     gpu_state: self.gpu_state.clone(),
+                                     clock: self.clock.clone(),
+                                     abort_key: self.abort_key.clone(),
+                                     shutdown_hooks: self.shutdown_hooks.clone() };
 
         // // start renderer
         // {
@@ -647,9 +847,15 @@ impl MainLoop {
         //     });
         // }
 
-        // start experiment
+        // start experiment. The thread holds the sender until it is done (including the
+        // shutdown hooks), so the event loop can wait for it before exiting
+        let shutdown_hooks = self.shutdown_hooks.clone();
+        let (experiment_running, experiment_finished) = std::sync::mpsc::channel::<()>();
         thread::spawn(move || {
+            let _experiment_running = experiment_running;
             let res = experiment_fn(wm);
+            // give the experiment a chance to clean up
+            shutdown_hooks.run();
             // panic if the experiment function returns an error
             if let Err(e) = res {
                 // throw error
@@ -662,6 +868,9 @@ impl MainLoop {
         // set event loop to poll
         event_loop.set_control_flow(ControlFlow::Poll);
 
+        // the hooks are also called when the event loop exits, e.g. when the experiment was aborted
+        let shutdown_hooks = self.shutdown_hooks.clone();
+
         let _ = event_loop.run(move |event: WinitEvent<PsyEventLoopEvent>, win_target| {
                               match event {
                                   WinitEvent::UserEvent(event) => {
@@ -718,10 +927,18 @@ impl MainLoop {
                                           window_state.config.width = new_size.width.max(1);
                                           window_state.config.height = new_size.height.max(1);
 
-                                          window_state.surface.configure(&gpu_state.device, &window_state.config);
+                                          // drop the swapchain texture we might be holding, as the
+                                          // surface cannot be reconfigured while it is alive
+                                          window_state.next_surface_texture.lock_blocking().take();
+
+                                          if let Some(surface) = &window_state.surface {
+                                              surface.configure(&gpu_state.device, &window_state.config);
+                                          }
 
                                           // on macos, the window size is not updated automatically
-                                          window_state.window.request_redraw();
+                                          if let Some(winit_window) = &window_state.window {
+                                              winit_window.request_redraw();
+                                          }
 
                                           // update window size
                                           window.width_px.store(new_size.width as u32, Ordering::Relaxed);
@@ -733,20 +950,50 @@ impl MainLoop {
                                   WinitEvent::WindowEvent { window_id: id, event } => {
                                       if let Some(window) = self.get_window_by_id(id) {
                                           if let Some(input) = Event::try_from_winit(event.clone(), &window).ok() {
-                                              // if escape key was pressed, close window
-                                              if input.key_pressed("\u{1b}") {
-                                                  win_target.exit();
-                                              }
+                                              // derive drag events from mouse button and cursor events
+                                              let drag = window.pointer.lock_blocking().update(&event, &input);
+
+                                              let abort_requested = self.abort_key.lock_blocking().is_some_and(|abort_key| abort_key.matches(&input));
 
                                               // broadcast event to window
                                               window.event_broadcast_sender.try_broadcast(input.clone());
 
                                               // dispatch_event to window
                                               // note: this should be done in a separate thread using the winndow's event_broadcast channel
-                                              window.dispatch_event(input);
+                                              window.dispatch_event(input.clone());
+
+                                              if let Some(drag) = drag {
+                                                  window.event_broadcast_sender.try_broadcast(drag.clone());
+                                                  window.dispatch_event(drag);
+                                              }
+
+                                              // if the abort key combination was pressed, let the experiment know and
+                                              // shut down unless the experiment handles the request itself
+                                              if abort_requested {
+                                                  log::debug!("Abort key combination pressed");
+
+                                                  let abort = Event::AbortRequested { timestamp: *input.timestamp() };
+                                                  window.event_broadcast_sender.try_broadcast(abort.clone());
+
+                                                  if !window.dispatch_event(abort) {
+                                                      win_target.exit();
+                                                  }
+                                              }
                                           }
                                       }
                                   }
+                                  // wait for the experiment to finish before cleaning up, so that the hooks do
+                                  // not run while the experiment is still using what they clean up. This runs
+                                  // inside the event loop, as `run` never returns on some platforms
+                                  WinitEvent::LoopExiting => {
+                                      if matches!(experiment_finished.recv_timeout(ABORT_TIMEOUT),
+                                                  Err(std::sync::mpsc::RecvTimeoutError::Timeout))
+                                      {
+                                          log::warn!("Experiment did not finish within {:?} after the event loop exited, running shutdown hooks anyway",
+                                                     ABORT_TIMEOUT);
+                                      }
+                                      shutdown_hooks.run();
+                                  }
                                   // handle close event
                                   _ => {}
                               }
@@ -755,7 +1002,7 @@ impl MainLoop {
 
     pub fn get_window_by_id(&self, id: winit::window::WindowId) -> Option<Window> {
         for window in &self.windows {
-            if window.read_window_state_blocking().window.id() == id {
+            if window.read_window_state_blocking().window.as_ref().map(|w| w.id()) == Some(id) {
                 return Some(window.clone());
             }
         }
@@ -0,0 +1,112 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! LabStreamingLayer (LSL) marker streams (requires the `lsl` feature, which
+//! links against liblsl). An `LslMarkerOutlet` publishes markers (e.g.
+//! stimulus onsets or trial information) as an LSL stream of type `Markers`,
+//! so LSL recorders such as LabRecorder can record them alongside EEG or
+//! eye-tracking data and synchronise them using LSL's clock synchronisation.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # fn run(window: &psybee::visual::Window) -> Result<(), psybee::errors::PsybeeError> {
+//! use psybee::lsl::LslMarkerOutlet;
+//!
+//! let mut outlet = LslMarkerOutlet::new("psybee-markers", "psybee-experiment-1")?;
+//!
+//! let info = window.present(window.get_frame());
+//! outlet.push_at("stimulus_onset", info.onset())?;
+//! outlet.push(42)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ::lsl::{ExPushable, StreamInfo, StreamOutlet};
+
+use crate::clock::Timestamp;
+use crate::errors::PsybeeError;
+use crate::markers::Marker;
+use crate::triggers::TriggerOutput;
+use crate::utils::IntoStringVector;
+
+/// The type of the LSL streams created by `LslMarkerOutlet`.
+const STREAM_TYPE: &str = "Markers";
+
+/// Publishes markers as an irregular, single-channel LSL string stream.
+/// Integer markers are published as their decimal representation.
+pub struct LslMarkerOutlet {
+    name: String,
+    outlet: StreamOutlet,
+}
+
+impl std::fmt::Debug for LslMarkerOutlet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LslMarkerOutlet").field("name", &self.name).finish()
+    }
+}
+
+impl LslMarkerOutlet {
+    /// Create a new outlet with the given stream name. The source id should
+    /// uniquely identify the experiment (e.g. include the subject and session),
+    /// so recorders can reconnect to the stream if the experiment is restarted.
+    pub fn new(name: &str, source_id: &str) -> Result<Self, PsybeeError> {
+        let info = StreamInfo::new(name, STREAM_TYPE, 1, ::lsl::IRREGULAR_RATE, ::lsl::ChannelFormat::String, source_id)?;
+        let outlet = StreamOutlet::new(&info, 1, 360)?;
+
+        Ok(Self { name: name.to_string(),
+                  outlet })
+    }
+
+    /// Returns the name of the stream.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if at least one recorder is connected to the stream.
+    pub fn has_consumers(&self) -> bool {
+        self.outlet.have_consumers()
+    }
+
+    /// Publish a marker that occurs now.
+    pub fn push(&mut self, marker: impl Into<Marker>) -> Result<(), PsybeeError> {
+        self.push_at(marker, Timestamp::now())
+    }
+
+    /// Publish a marker that occurred at the given time, e.g. the onset of a
+    /// frame (see `PresentationInfo::onset()`) or the timestamp of an input
+    /// event. The time is converted to the LSL clock.
+    pub fn push_at(&mut self, marker: impl Into<Marker>, time: Timestamp) -> Result<(), PsybeeError> {
+        let lsl_time = ::lsl::local_clock() - Timestamp::now().secs_since(time);
+        self.outlet.push_sample_ex(&vec![marker.into().to_string()], lsl_time, true)?;
+        Ok(())
+    }
+
+    /// Log an event that occurs now. The column values are joined with tabs
+    /// and published as a single marker.
+    pub fn log<I>(&mut self, column_values: I) -> Result<(), PsybeeError>
+        where I: IntoStringVector
+    {
+        self.log_at(column_values, Timestamp::now())
+    }
+
+    /// Log an event that occurred at the given time (see `log()`).
+    pub fn log_at<I>(&mut self, column_values: I, time: Timestamp) -> Result<(), PsybeeError>
+        where I: IntoStringVector
+    {
+        self.push_at(column_values.into_string_vec().join("\t"), time)
+    }
+}
+
+/// Trigger codes sent to an outlet (e.g. frame triggers, see
+/// `Frame::add_trigger()`) are published as markers.
+impl TriggerOutput for LslMarkerOutlet {
+    fn write(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        let timestamp = Timestamp::now();
+        self.push_at(code, timestamp)?;
+        Ok(timestamp)
+    }
+}
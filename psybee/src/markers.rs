@@ -0,0 +1,310 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Marker streams over UDP. A `UdpMarkerSender` publishes string and integer
+//! markers (e.g. stimulus onsets or trial information) to other processes on
+//! the same or another machine, and a `UdpMarkerReceiver` receives them. This
+//! uses a simple psybee-specific format and is not compatible with
+//! LabStreamingLayer (LSL); to send markers to LSL recorders (e.g.
+//! LabRecorder), use `psybee::lsl::LslMarkerOutlet` (requires the `lsl`
+//! feature).
+//!
+//! Each marker is sent as a single datagram of tab-separated UTF-8 text:
+//!
+//! ```text
+//! PSYBEE-MARKER  1  <stream name>  <sequence number>  <time>  <send time>  <i|s>  <value>
+//! ```
+//!
+//! Times are in seconds on the clock of the sender (usually the experiment
+//! clock, see `UdpMarkerSender::new()`). As the difference between the
+//! two is known, receivers can map the time of the
+//! marker to their own clock (up to the network latency). The value is the
+//! last field and may itself contain tabs.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # fn run(em: &psybee::ExperimentManager, window: &psybee::visual::Window) -> Result<(), psybee::errors::PsybeeError> {
+//! use psybee::markers::UdpMarkerSender;
+//!
+//! let mut sender = UdpMarkerSender::new("psybee-markers", "127.0.0.1:16600", em.clock().clone())?;
+//!
+//! let info = window.present(window.get_frame());
+//! sender.push_at("stimulus_onset", info.onset())?;
+//! sender.push(42)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Display;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use crate::clock::{Clock, Timestamp};
+use crate::errors::PsybeeError;
+use crate::triggers::TriggerOutput;
+use crate::utils::IntoStringVector;
+
+/// Identifies datagrams sent by a `UdpMarkerSender`.
+const MAGIC: &str = "PSYBEE-MARKER";
+
+/// Version of the datagram format.
+const VERSION: &str = "1";
+
+/// Maximum size of a datagram (the maximum payload of a UDP datagram).
+const MAX_DATAGRAM_SIZE: usize = 65507;
+
+/// A marker, i.e. the value of a single sample of a marker stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Marker {
+    Int(i64),
+    String(String),
+}
+
+impl Display for Marker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Marker::Int(value) => write!(f, "{}", value),
+            Marker::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<&str> for Marker {
+    fn from(value: &str) -> Self {
+        Marker::String(value.to_string())
+    }
+}
+
+impl From<String> for Marker {
+    fn from(value: String) -> Self {
+        Marker::String(value)
+    }
+}
+
+impl From<i64> for Marker {
+    fn from(value: i64) -> Self {
+        Marker::Int(value)
+    }
+}
+
+impl From<i32> for Marker {
+    fn from(value: i32) -> Self {
+        Marker::Int(value as i64)
+    }
+}
+
+impl From<u8> for Marker {
+    fn from(value: u8) -> Self {
+        Marker::Int(value as i64)
+    }
+}
+
+/// Publishes markers to one or more receivers over UDP.
+#[derive(Debug)]
+pub struct UdpMarkerSender {
+    name: String,
+    socket: UdpSocket,
+    receivers: Vec<SocketAddr>,
+    sequence: u64,
+    clock: Clock,
+}
+
+impl UdpMarkerSender {
+    /// Create a new sender with the given stream name that sends markers to the
+    /// given address (e.g. `127.0.0.1:16600`). The times of markers are
+    /// published on the given clock, which should be the experiment clock (see
+    /// `ExperimentManager::clock()`). More receivers can be added with
+    /// `add_receiver()`.
+    pub fn new<A: ToSocketAddrs>(name: &str, receiver: A, clock: Clock) -> Result<Self, PsybeeError> {
+        let receiver = receiver.to_socket_addrs()?
+                               .next()
+                               .ok_or_else(|| PsybeeError::MarkerStreamError("no receiver address given".to_string()))?;
+
+        let socket = match receiver {
+            SocketAddr::V4(_) => UdpSocket::bind("0.0.0.0:0")?,
+            SocketAddr::V6(_) => UdpSocket::bind("[::]:0")?,
+        };
+
+        Ok(Self { name: name.to_string(),
+                  socket,
+                  receivers: vec![receiver],
+                  sequence: 0,
+                  clock })
+    }
+
+    /// Send markers to an additional receiver.
+    pub fn add_receiver<A: ToSocketAddrs>(&mut self, receiver: A) -> Result<(), PsybeeError> {
+        let receiver = receiver.to_socket_addrs()?
+                               .next()
+                               .ok_or_else(|| PsybeeError::MarkerStreamError("no receiver address given".to_string()))?;
+        self.receivers.push(receiver);
+        Ok(())
+    }
+
+    /// Returns the name of the stream.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the clock the times of markers are measured on.
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Publish a marker that occurs now.
+    pub fn push(&mut self, marker: impl Into<Marker>) -> Result<(), PsybeeError> {
+        self.push_at(marker, Timestamp::now())
+    }
+
+    /// Publish a marker that occurred at the given time, e.g. the onset of a
+    /// frame (see `PresentationInfo::onset()`) or the timestamp of an input
+    /// event.
+    pub fn push_at(&mut self, marker: impl Into<Marker>, time: Timestamp) -> Result<(), PsybeeError> {
+        let (kind, value) = match marker.into() {
+            Marker::Int(value) => ("i", value.to_string()),
+            Marker::String(value) => ("s", value),
+        };
+
+        let datagram = [MAGIC,
+                        VERSION,
+                        &self.name,
+                        &self.sequence.to_string(),
+                        &self.clock.secs(time).to_string(),
+                        &self.clock.secs(Timestamp::now()).to_string(),
+                        kind,
+                        &value].join("\t");
+
+        if datagram.len() > MAX_DATAGRAM_SIZE {
+            return Err(PsybeeError::MarkerStreamError(format!("encoded marker is too long ({} bytes, at most {} bytes are allowed)",
+                                                              datagram.len(),
+                                                              MAX_DATAGRAM_SIZE)));
+        }
+
+        for receiver in &self.receivers {
+            self.socket.send_to(datagram.as_bytes(), receiver)?;
+        }
+        self.sequence += 1;
+
+        Ok(())
+    }
+
+    /// Log an event that occurs now. The column values are joined with tabs
+    /// and published as a string marker, so a sender can be used alongside
+    /// (or instead of) a `CSVEventLogger`.
+    pub fn log<I>(&mut self, column_values: I) -> Result<(), PsybeeError>
+        where I: IntoStringVector
+    {
+        self.log_at(column_values, Timestamp::now())
+    }
+
+    /// Log an event that occurred at the given time (see `log()`).
+    pub fn log_at<I>(&mut self, column_values: I, time: Timestamp) -> Result<(), PsybeeError>
+        where I: IntoStringVector
+    {
+        self.push_at(column_values.into_string_vec().join("\t"), time)
+    }
+
+    /// Markers are sent immediately, so this does nothing. It exists so a
+    /// sender can be flushed like the file-based event loggers.
+    pub fn flush(&mut self) -> Result<(), PsybeeError> {
+        Ok(())
+    }
+}
+
+/// Trigger codes sent to a sender (e.g. frame triggers, see
+/// `Frame::add_trigger()`) are published as integer markers.
+impl TriggerOutput for UdpMarkerSender {
+    fn write(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        let timestamp = Timestamp::now();
+        self.push_at(code, timestamp)?;
+        Ok(timestamp)
+    }
+}
+
+/// A marker received by a `UdpMarkerReceiver`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedMarker {
+    /// The name of the stream the marker was published on.
+    pub stream: String,
+    /// The sequence number of the marker within the stream (starting at 0).
+    /// Gaps indicate lost datagrams.
+    pub sequence: u64,
+    /// The marker.
+    pub marker: Marker,
+    /// The time of the marker on the clock of the sender, in seconds.
+    pub sender_time: f64,
+    /// The time of the marker on the clock of the receiver. This does not
+    /// account for the network latency.
+    pub timestamp: Timestamp,
+}
+
+/// Receives markers published by `UdpMarkerSender`s.
+#[derive(Debug)]
+pub struct UdpMarkerReceiver {
+    socket: UdpSocket,
+}
+
+impl UdpMarkerReceiver {
+    /// Listen for markers on the given address (e.g. `127.0.0.1:16600`, or
+    /// `127.0.0.1:0` to let the operating system choose a port).
+    pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Self, PsybeeError> {
+        Ok(Self { socket: UdpSocket::bind(address)? })
+    }
+
+    /// Returns the address the receiver is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr, PsybeeError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Wait for the next marker. Returns None if no marker arrived within
+    /// the timeout, or blocks indefinitely if the timeout is None.
+    pub fn recv(&self, timeout: Option<Duration>) -> Result<Option<ReceivedMarker>, PsybeeError> {
+        self.socket.set_read_timeout(timeout)?;
+
+        let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+        let n = match self.socket.recv(&mut buffer) {
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let received = Timestamp::now();
+
+        let datagram = std::str::from_utf8(&buffer[..n]).map_err(|_| invalid_datagram("not valid UTF-8"))?;
+        let fields: Vec<&str> = datagram.splitn(8, '\t').collect();
+
+        if fields.len() != 8 || fields[0] != MAGIC {
+            return Err(invalid_datagram("not a marker"));
+        }
+        if fields[1] != VERSION {
+            return Err(invalid_datagram("unsupported version"));
+        }
+
+        let sequence = fields[3].parse().map_err(|_| invalid_datagram("invalid sequence number"))?;
+        let sender_time: f64 = fields[4].parse().map_err(|_| invalid_datagram("invalid time"))?;
+        let send_time: f64 = fields[5].parse().map_err(|_| invalid_datagram("invalid send time"))?;
+
+        let marker = match fields[6] {
+            "i" => Marker::Int(fields[7].parse().map_err(|_| invalid_datagram("invalid integer marker"))?),
+            "s" => Marker::String(fields[7].to_string()),
+            _ => return Err(invalid_datagram("invalid marker type")),
+        };
+
+        // the marker occurred this long before it was sent (unless that would be
+        // before the start of the monotonic clock)
+        let age = Duration::try_from_secs_f64(send_time - sender_time).unwrap_or_default();
+
+        Ok(Some(ReceivedMarker { stream: fields[2].to_string(),
+                                 sequence,
+                                 marker,
+                                 sender_time,
+                                 timestamp: received.checked_sub(age).unwrap_or(received) }))
+    }
+}
+
+fn invalid_datagram(reason: &str) -> PsybeeError {
+    PsybeeError::MarkerStreamError(format!("received an invalid datagram: {}", reason))
+}
@@ -29,6 +29,10 @@ pub enum GPUBackend {
     OpenGL,
 }
 
+/// How `Window::present()` blocks when submitting a frame.
+///
+/// Note that scanline queries are not implemented yet, so the vertical blanking interval cannot be observed
+/// directly (see the individual variants for what they currently wait for).
 #[derive(Debug, Clone, Copy)]
 pub enum BlockingStrategy {
     /// Will render the current frame using a command buffer and submit it to the GPU, then immediately return.
@@ -36,19 +40,21 @@ pub enum BlockingStrategy {
     /// too many frames in short succession, this will block until the GPU has caught up with the work.
     DoNotBlock,
 
-    // Will block until the start of the vertical blanking interval at which the current frame will be presented,
-    /// then return as quickly as possible. On windows, this will use the `D3DKMTGetScanLine` function to find the
-    /// start of the vertical blanking interval.
+    /// Will block until the start of the vertical blanking interval at which the current frame will be presented,
+    /// then return as quickly as possible. As scanline queries are not implemented yet, this currently only blocks
+    /// until the GPU has finished rendering the frame, i.e. until the frame is queued for presentation, and returns
+    /// before the frame is flipped to the screen.
     BlockUntilVBlankStart,
 
     /// Will block until the end of the vertical blanking interval at which the current frame will be presented,
-    /// then return as quickly as possible. On windows, this will use the `D3DKMTGetScanLine` function to find the
-    /// end of the vertical blanking interval, while on Vulkan it will use the `VK_GOOGLE_display_timing` extension.
+    /// then return as quickly as possible. This blocks until the next swapchain texture can be acquired, which
+    /// (with a maximum frame latency of 1) happens once the frame has been flipped to the screen.
     BlockUntilVBlankEnd,
 
-    /// Will block until the end of the vertical blanking interval at which the current frame will be presented,
-    /// then verify that the frame has been presented and if any frames have been missed. This will only be available
-    /// on platforms that support querying the current scanline.
+    /// Like `BlockUntilVBlankEnd`, but additionally verifies that the frame was flipped at a vertical blank, i.e.
+    /// that the interval since the previous flip is consistent with the refresh rate. A warning is logged if
+    /// presentation does not seem to be synchronised to the vertical blank (e.g. because vsync is disabled), in
+    /// which case neither the flip times nor the detection of dropped frames can be trusted.
     BlockUntilVBlankEndVerified,
 }
 
@@ -0,0 +1,473 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Maximum-likelihood fitting of psychometric functions.
+//!
+//! The psychometric function is modelled as
+//! `psi(x) = guess_rate + (1 - guess_rate - lapse_rate) * F(x; threshold,
+//! slope)`, where `F` is a cumulative Gaussian, logistic or Weibull function.
+//! Confidence intervals are obtained with a parametric bootstrap.
+//!
+//! As `CSVEventLogger` and `BIDSEventLogger` flush every event to disk, the
+//! responses of the current session can be loaded with `load_responses()` at
+//! any time, e.g. to check the threshold between blocks:
+//!
+//! ```no_run
+//! use psybee::psychometric::{fit, load_responses, FitOptions, PsychometricFunction};
+//!
+//! let data = load_responses("sub-01_task-contrast_events.tsv", "contrast", "correct").unwrap();
+//! let fit = fit(&data,
+//!               &FitOptions { function: PsychometricFunction::Weibull,
+//!                             guess_rate: 0.5,
+//!                             n_bootstrap: 500,
+//!                             ..Default::default() }).unwrap();
+//!
+//! println!("Threshold: {} (95% CI: {:?})", fit.threshold, fit.threshold_ci);
+//! ```
+
+use std::collections::BTreeMap;
+
+use rand::{Rng, SeedableRng};
+
+use crate::errors::PsybeeError;
+
+/// The shape of the psychometric function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsychometricFunction {
+    /// `F(x) = Phi(slope * (x - threshold))`, i.e. a cumulative Gaussian with
+    /// mean `threshold` and standard deviation `1 / slope`.
+    CumulativeGaussian,
+    /// `F(x) = 1 / (1 + exp(-slope * (x - threshold)))`.
+    Logistic,
+    /// `F(x) = 1 - exp(-(x / threshold)^slope)`. Only defined for positive
+    /// intensities (`F(x) = 0` for `x <= 0`).
+    Weibull,
+}
+
+impl PsychometricFunction {
+    /// Evaluates the (unscaled) function `F` at `x`.
+    pub fn eval(self, x: f64, threshold: f64, slope: f64) -> f64 {
+        match self {
+            PsychometricFunction::CumulativeGaussian => 0.5 * erfc(-slope * (x - threshold) / std::f64::consts::SQRT_2),
+            PsychometricFunction::Logistic => 1.0 / (1.0 + (-slope * (x - threshold)).exp()),
+            PsychometricFunction::Weibull => {
+                if x <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - (-(x / threshold).powf(slope)).exp()
+                }
+            }
+        }
+    }
+
+    /// Inverse of `F`, i.e. the intensity at which `F(x) = p`.
+    pub fn inverse(self, p: f64, threshold: f64, slope: f64) -> f64 {
+        match self {
+            PsychometricFunction::CumulativeGaussian => threshold + std::f64::consts::SQRT_2 * erf_inv(2.0 * p - 1.0) / slope,
+            PsychometricFunction::Logistic => threshold + (p / (1.0 - p)).ln() / slope,
+            PsychometricFunction::Weibull => threshold * (-(1.0 - p).ln()).powf(1.0 / slope),
+        }
+    }
+}
+
+/// Responses at a single stimulus intensity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PsychometricData {
+    pub intensity: f64,
+    pub n_trials: u32,
+    pub n_correct: u32,
+}
+
+impl PsychometricData {
+    /// Aggregates single-trial responses (intensity, correct) by intensity.
+    pub fn from_responses<I>(responses: I) -> Vec<Self>
+        where I: IntoIterator<Item = (f64, bool)>
+    {
+        // f64 is not Ord, so we use the bit pattern as key and sort afterwards
+        let mut data: BTreeMap<u64, Self> = BTreeMap::new();
+
+        for (intensity, correct) in responses {
+            let entry = data.entry(intensity.to_bits()).or_insert(Self { intensity,
+                                                                         n_trials: 0,
+                                                                         n_correct: 0 });
+            entry.n_trials += 1;
+            entry.n_correct += correct as u32;
+        }
+
+        let mut data = data.into_values().collect::<Vec<_>>();
+        data.sort_by(|a, b| a.intensity.total_cmp(&b.intensity));
+        data
+    }
+
+    /// Proportion of correct responses.
+    pub fn proportion_correct(&self) -> f64 {
+        self.n_correct as f64 / self.n_trials as f64
+    }
+}
+
+/// Loads single-trial responses from a file written by `CSVEventLogger` or
+/// `BIDSEventLogger` and aggregates them by intensity. Files ending in `.tsv`
+/// are read as tab-separated, all others as comma-separated. Responses may be
+/// given as `1`/`0` or `true`/`false`; rows with an empty intensity or response
+/// (e.g. other events in a BIDS events file) are skipped.
+pub fn load_responses<P>(path: P, intensity_column: &str, response_column: &str) -> Result<Vec<PsychometricData>, PsybeeError>
+    where P: AsRef<std::path::Path>
+{
+    let path = path.as_ref();
+    let delimiter = if path.extension().is_some_and(|ext| ext == "tsv") { b'\t' } else { b',' };
+
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_path(path)?;
+
+    let headers = reader.headers()?.clone();
+    let column_index = |name: &str| {
+        headers.iter()
+               .position(|h| h == name)
+               .ok_or_else(|| PsybeeError::ColumnNameDoesNotExistError(name.to_string()))
+    };
+    let intensity_index = column_index(intensity_column)?;
+    let response_index = column_index(response_column)?;
+
+    let mut responses = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let (Some(intensity), Some(response)) = (record.get(intensity_index), record.get(response_index)) else {
+            continue;
+        };
+        if intensity.is_empty() || response.is_empty() {
+            continue;
+        }
+
+        let intensity: f64 = intensity.parse()
+                                      .map_err(|_| PsybeeError::CustomError(format!("Invalid intensity: {}", intensity)))?;
+        let correct = match response.to_lowercase().as_str() {
+            "1" | "true" => true,
+            "0" | "false" => false,
+            _ => return Err(PsybeeError::CustomError(format!("Invalid response: {}", response))),
+        };
+
+        responses.push((intensity, correct));
+    }
+
+    Ok(PsychometricData::from_responses(responses))
+}
+
+/// Options for `fit()`.
+#[derive(Debug, Clone)]
+pub struct FitOptions {
+    /// The shape of the psychometric function.
+    pub function: PsychometricFunction,
+    /// Proportion of correct responses at very low intensities (e.g. 0.5 for
+    /// 2AFC, 0 for yes/no tasks).
+    pub guess_rate: f64,
+    /// Proportion of trials on which the observer responds blindly. If None,
+    /// the lapse rate is estimated (between 0 and `max_lapse_rate`).
+    pub lapse_rate: Option<f64>,
+    /// Upper bound of the estimated lapse rate.
+    pub max_lapse_rate: f64,
+    /// Number of bootstrap samples used for the confidence intervals. No
+    /// confidence intervals are computed if this is 0.
+    pub n_bootstrap: usize,
+    /// Coverage of the confidence intervals.
+    pub confidence_level: f64,
+    /// Seed for the bootstrap. If None, a random seed is used.
+    pub seed: Option<u64>,
+}
+
+impl Default for FitOptions {
+    fn default() -> Self {
+        Self { function: PsychometricFunction::CumulativeGaussian,
+               guess_rate: 0.5,
+               lapse_rate: None,
+               max_lapse_rate: 0.06,
+               n_bootstrap: 0,
+               confidence_level: 0.95,
+               seed: None }
+    }
+}
+
+/// The result of fitting a psychometric function.
+#[derive(Debug, Clone)]
+pub struct PsychometricFit {
+    pub function: PsychometricFunction,
+    /// Threshold parameter of `F` (see `PsychometricFunction`).
+    pub threshold: f64,
+    /// Slope parameter of `F` (see `PsychometricFunction`).
+    pub slope: f64,
+    pub guess_rate: f64,
+    pub lapse_rate: f64,
+    /// Log likelihood of the data under the fitted function.
+    pub log_likelihood: f64,
+    /// Bootstrap confidence interval of the threshold.
+    pub threshold_ci: Option<(f64, f64)>,
+    /// Bootstrap confidence interval of the slope.
+    pub slope_ci: Option<(f64, f64)>,
+}
+
+impl PsychometricFit {
+    /// Predicted proportion of correct responses at intensity `x`.
+    pub fn eval(&self, x: f64) -> f64 {
+        self.guess_rate + (1.0 - self.guess_rate - self.lapse_rate) * self.function.eval(x, self.threshold, self.slope)
+    }
+
+    /// Intensity at which the predicted proportion of correct responses is
+    /// `p`. Returns None if `p` is outside the range of the function.
+    pub fn intensity_at(&self, p: f64) -> Option<f64> {
+        let f = (p - self.guess_rate) / (1.0 - self.guess_rate - self.lapse_rate);
+        (f > 0.0 && f < 1.0).then(|| self.function.inverse(f, self.threshold, self.slope))
+    }
+}
+
+/// Fits a psychometric function to the data by maximum likelihood.
+pub fn fit(data: &[PsychometricData], options: &FitOptions) -> Result<PsychometricFit, PsybeeError> {
+    let data = data.iter().copied().filter(|d| d.n_trials > 0).collect::<Vec<_>>();
+
+    if data.len() < 2 {
+        return Err(PsybeeError::PsychometricFitError("At least two distinct intensities are required.".to_string()));
+    }
+    if data.iter().any(|d| d.n_correct > d.n_trials) {
+        return Err(PsybeeError::PsychometricFitError("The number of correct responses exceeds the number of trials.".to_string()));
+    }
+    if options.function == PsychometricFunction::Weibull && data.iter().all(|d| d.intensity <= 0.0) {
+        return Err(PsybeeError::PsychometricFitError("The Weibull function requires positive intensities.".to_string()));
+    }
+    if !(0.0..1.0).contains(&options.guess_rate) || options.lapse_rate.is_some_and(|l| !(0.0..1.0 - options.guess_rate).contains(&l)) {
+        return Err(PsybeeError::PsychometricFitError("Invalid guess or lapse rate.".to_string()));
+    }
+
+    let (threshold, slope, lapse_rate, log_likelihood) = fit_ml(&data, options);
+
+    let mut result = PsychometricFit { function: options.function,
+                                       threshold,
+                                       slope,
+                                       guess_rate: options.guess_rate,
+                                       lapse_rate,
+                                       log_likelihood,
+                                       threshold_ci: None,
+                                       slope_ci: None };
+
+    if options.n_bootstrap > 0 {
+        // parametric bootstrap: simulate new data sets from the fitted function and refit
+        let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+
+        let mut thresholds = Vec::with_capacity(options.n_bootstrap);
+        let mut slopes = Vec::with_capacity(options.n_bootstrap);
+
+        for _ in 0..options.n_bootstrap {
+            let sample = data.iter()
+                             .map(|d| {
+                                 let p = result.eval(d.intensity);
+                                 PsychometricData { n_correct: (0..d.n_trials).filter(|_| rng.gen_bool(p)).count() as u32,
+                                                    ..*d }
+                             })
+                             .collect::<Vec<_>>();
+
+            let (threshold, slope, _, _) = fit_ml(&sample, options);
+            thresholds.push(threshold);
+            slopes.push(slope);
+        }
+
+        let alpha = (1.0 - options.confidence_level) / 2.0;
+        result.threshold_ci = Some((quantile(&mut thresholds, alpha), quantile(&mut thresholds, 1.0 - alpha)));
+        result.slope_ci = Some((quantile(&mut slopes, alpha), quantile(&mut slopes, 1.0 - alpha)));
+    }
+
+    Ok(result)
+}
+
+/// Returns the maximum-likelihood estimates of (threshold, slope, lapse rate)
+/// and the log likelihood.
+fn fit_ml(data: &[PsychometricData], options: &FitOptions) -> (f64, f64, f64, f64) {
+    let function = options.function;
+    let log_threshold = function == PsychometricFunction::Weibull;
+
+    // the parameters are transformed so that the optimisation is unconstrained:
+    // [threshold (log for Weibull), log slope, logit of the lapse rate]
+    let unpack = |params: &[f64]| {
+        let threshold = if log_threshold { params[0].exp() } else { params[0] };
+        let slope = params[1].exp();
+        let lapse_rate = options.lapse_rate
+                                .unwrap_or_else(|| options.max_lapse_rate / (1.0 + (-params[2]).exp()));
+        (threshold, slope, lapse_rate)
+    };
+
+    let neg_log_likelihood = |params: &[f64]| {
+        let (threshold, slope, lapse_rate) = unpack(params);
+        -data.iter()
+             .map(|d| {
+                 let p = options.guess_rate + (1.0 - options.guess_rate - lapse_rate) * function.eval(d.intensity, threshold, slope);
+                 let p = p.clamp(1e-10, 1.0 - 1e-10);
+                 d.n_correct as f64 * p.ln() + (d.n_trials - d.n_correct) as f64 * (1.0 - p).ln()
+             })
+             .sum::<f64>()
+    };
+
+    // find a good starting point on a coarse grid to avoid local minima
+    let intensities = data.iter()
+                          .map(|d| d.intensity)
+                          .filter(|&x| !log_threshold || x > 0.0)
+                          .collect::<Vec<_>>();
+    let min = intensities.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = intensities.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    let (lo, hi) = if log_threshold { (min.ln(), max.ln()) } else { (min, max) };
+    let spread = (hi - lo).max(1e-6);
+    // slopes for the Weibull function are unitless, otherwise they scale with the
+    // intensity range
+    let slope_scale = if log_threshold { 1.0 } else { 1.0 / spread };
+
+    let mut start = vec![lo, 0.0, 0.0];
+    let mut best = f64::INFINITY;
+    for i in 0..=20 {
+        for j in 0..=20 {
+            let params = [lo + spread * i as f64 / 20.0, (slope_scale * 10f64.powf(-0.5 + 2.0 * j as f64 / 20.0)).ln(), 0.0];
+            let value = neg_log_likelihood(&params);
+            if value < best {
+                best = value;
+                start = params.to_vec();
+            }
+        }
+    }
+
+    let n_params = if options.lapse_rate.is_some() { 2 } else { 3 };
+    let (params, value) = nelder_mead(|p| {
+                                          let mut full = [0.0; 3];
+                                          full[..n_params].copy_from_slice(p);
+                                          neg_log_likelihood(&full)
+                                      },
+                                      &start[..n_params]);
+
+    let mut full = [0.0; 3];
+    full[..n_params].copy_from_slice(&params);
+    let (threshold, slope, lapse_rate) = unpack(&full);
+
+    (threshold, slope, lapse_rate, -value)
+}
+
+/// Minimises `f` using the Nelder-Mead simplex algorithm. Returns the
+/// minimum and the function value at the minimum.
+fn nelder_mead<F>(f: F, start: &[f64]) -> (Vec<f64>, f64)
+    where F: Fn(&[f64]) -> f64
+{
+    const MAX_ITERATIONS: usize = 2000;
+    const TOLERANCE: f64 = 1e-10;
+
+    let n = start.len();
+
+    // initial simplex
+    let mut simplex: Vec<(Vec<f64>, f64)> = Vec::with_capacity(n + 1);
+    simplex.push((start.to_vec(), f(start)));
+    for i in 0..n {
+        let mut point = start.to_vec();
+        point[i] += if point[i].abs() > 1e-3 { 0.1 * point[i].abs() } else { 0.1 };
+        let value = f(&point);
+        simplex.push((point, value));
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        if (simplex[n].1 - simplex[0].1).abs() < TOLERANCE {
+            break;
+        }
+
+        // centroid of all points except the worst
+        let centroid = (0..n).map(|i| simplex[..n].iter().map(|(p, _)| p[i]).sum::<f64>() / n as f64)
+                             .collect::<Vec<_>>();
+        let towards = |t: f64| {
+            centroid.iter()
+                    .zip(&simplex[n].0)
+                    .map(|(c, w)| c + t * (w - c))
+                    .collect::<Vec<_>>()
+        };
+
+        let reflected = towards(-1.0);
+        let reflected_value = f(&reflected);
+
+        if reflected_value < simplex[0].1 {
+            let expanded = towards(-2.0);
+            let expanded_value = f(&expanded);
+            simplex[n] = if expanded_value < reflected_value { (expanded, expanded_value) } else { (reflected, reflected_value) };
+        } else if reflected_value < simplex[n - 1].1 {
+            simplex[n] = (reflected, reflected_value);
+        } else {
+            let contracted = if reflected_value < simplex[n].1 { towards(-0.5) } else { towards(0.5) };
+            let contracted_value = f(&contracted);
+
+            if contracted_value < simplex[n].1.min(reflected_value) {
+                simplex[n] = (contracted, contracted_value);
+            } else {
+                // shrink towards the best point
+                let best = simplex[0].0.clone();
+                for (point, value) in simplex.iter_mut().skip(1) {
+                    for (x, b) in point.iter_mut().zip(&best) {
+                        *x = b + 0.5 * (*x - b);
+                    }
+                    *value = f(point);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex.swap_remove(0)
+}
+
+/// Returns the `q` quantile of the values (sorts the values in place).
+fn quantile(values: &mut [f64], q: f64) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let index = q * (values.len() - 1) as f64;
+    let (lower, upper) = (index.floor() as usize, index.ceil() as usize);
+    values[lower] + (index - lower as f64) * (values[upper] - values[lower])
+}
+
+/// Complementary error function (Numerical Recipes, fractional error below
+/// 1.2e-7).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+            * (-z * z - 1.26551223
+               + t * (1.00002368
+                      + t * (0.37409196
+                             + t * (0.09678418
+                                    + t * (-0.18628806 + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277)))))))))
+               .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+/// Inverse error function, refined with Newton's method.
+fn erf_inv(y: f64) -> f64 {
+    if y <= -1.0 {
+        return f64::NEG_INFINITY;
+    }
+    if y >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    // initial approximation (Giles, 2010)
+    let w = -((1.0 - y) * (1.0 + y)).ln();
+    let mut x = if w < 5.0 {
+        let w = w - 2.5;
+        let p = [2.81022636e-08, 3.43273939e-07, -3.5233877e-06, -4.39150654e-06, 0.00021858087, -0.00125372503, -0.00417768164, 0.246640727, 1.50140941];
+        p.iter().fold(0.0, |acc, c| acc * w + c) * y
+    } else {
+        let w = w.sqrt() - 3.0;
+        let p = [-0.000200214257, 0.000100950558, 0.00134934322, -0.00367342844, 0.00573950773, -0.0076224613, 0.00943887047, 1.00167406, 2.83297682];
+        p.iter().fold(0.0, |acc, c| acc * w + c) * y
+    };
+
+    for _ in 0..2 {
+        let err = (1.0 - erfc(x)) - y;
+        x -= err / (2.0 / std::f64::consts::PI.sqrt() * (-x * x).exp());
+    }
+
+    x
+}
@@ -0,0 +1,180 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Serial port devices (requires the `serial` feature). A `TriggerPort`
+//! sends event codes (e.g. EEG/MEG triggers) to a trigger box or amplifier,
+//! and a `SerialResponseBox` turns the bytes sent by a response box into
+//! timestamped events.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # fn run(window: &psybee::visual::Window) -> Result<(), psybee::errors::PsybeeError> {
+//! use std::time::Duration;
+//!
+//! use psybee::serial::{SerialResponseBox, TriggerPort};
+//!
+//! let mut triggers = TriggerPort::new("/dev/ttyUSB0", 115200)?;
+//! let _response_box = SerialResponseBox::new(window, "/dev/ttyUSB1", 9600)?;
+//!
+//! window.present(window.get_frame());
+//! triggers.pulse(42, Duration::from_millis(10))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io::{ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+use crate::clock::Timestamp;
+use crate::errors::PsybeeError;
+use crate::input::{Event, EventHandlingExt};
+use crate::triggers::TriggerOutput;
+use crate::visual::Window;
+
+/// How long a read from the response box blocks before the input thread
+/// checks whether it should stop.
+const READ_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Sends event codes through a serial port, one byte per code.
+pub struct TriggerPort {
+    port: Box<dyn SerialPort>,
+}
+
+impl std::fmt::Debug for TriggerPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TriggerPort").field("port", &self.port.name()).finish()
+    }
+}
+
+impl TriggerPort {
+    /// Open the serial port at the given path (e.g. `/dev/ttyUSB0` or `COM3`)
+    /// with the given baud rate.
+    pub fn new(path: &str, baud_rate: u32) -> Result<Self, PsybeeError> {
+        let port = serialport::new(path, baud_rate).open()?;
+        Ok(Self::from_port(port))
+    }
+
+    /// Use an already opened serial port.
+    pub fn from_port(port: Box<dyn SerialPort>) -> Self {
+        Self { port }
+    }
+
+    /// Send the given code and return the time it was written to the port.
+    /// Note that the code stays on the output lines of most trigger boxes
+    /// until the next code is sent (see `pulse()`).
+    pub fn send(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        self.port.write_all(&[code])?;
+        self.port.flush()?;
+        Ok(Timestamp::now())
+    }
+
+    /// Send the given code, wait for `duration` and reset the output to 0.
+    /// Returns the time the code was written to the port. This blocks for the
+    /// duration of the pulse.
+    pub fn pulse(&mut self, code: u8, duration: Duration) -> Result<Timestamp, PsybeeError> {
+        let timestamp = self.send(code)?;
+        std::thread::sleep(duration);
+        self.send(0)?;
+        Ok(timestamp)
+    }
+
+    /// Returns the name of the port, if available.
+    pub fn name(&self) -> Option<String> {
+        self.port.name()
+    }
+}
+
+impl TriggerOutput for TriggerPort {
+    fn write(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        TriggerPort::send(self, code)
+    }
+}
+
+/// Reads bytes from a response box connected to a serial port and delivers
+/// them to a window as `Event::ResponseBoxInput` events, one per byte. Which
+/// byte corresponds to which button depends on the response box. Reading stops
+/// when this is dropped.
+#[derive(Debug)]
+pub struct SerialResponseBox {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SerialResponseBox {
+    /// Open the serial port at the given path (e.g. `/dev/ttyUSB0` or `COM3`)
+    /// with the given baud rate and start delivering events to the window.
+    pub fn new(window: &Window, path: &str, baud_rate: u32) -> Result<Self, PsybeeError> {
+        let port = serialport::new(path, baud_rate).open()?;
+        Self::from_port(window, port)
+    }
+
+    /// Start delivering events from an already opened serial port to the
+    /// window.
+    pub fn from_port(window: &Window, mut port: Box<dyn SerialPort>) -> Result<Self, PsybeeError> {
+        port.set_timeout(READ_TIMEOUT)?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let name = port.name().unwrap_or_default();
+
+        let window = window.clone();
+        let running_clone = running.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut buffer = [0u8; 64];
+
+            while running_clone.load(Ordering::Relaxed) {
+                let n = match port.read(&mut buffer) {
+                    // the other end has been closed (e.g. the device was unplugged)
+                    Ok(0) => {
+                        log::error!("Response box {} was disconnected", name);
+                        break;
+                    }
+                    Ok(n) => n,
+                    Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        log::error!("Failed to read from response box {}: {}", name, e);
+                        break;
+                    }
+                };
+
+                // bytes that arrive together share a timestamp
+                let timestamp = Timestamp::now();
+
+                for byte in &buffer[..n] {
+                    let event = Event::ResponseBoxInput { timestamp,
+                                                          port: name.clone(),
+                                                          byte: *byte };
+                    let _ = window.event_broadcast_sender.try_broadcast(event.clone());
+                    window.dispatch_event(event);
+                }
+            }
+        });
+
+        Ok(Self { running,
+                  thread: Some(thread) })
+    }
+
+    /// Returns true while events are being read from the serial port, i.e.
+    /// until the response box is disconnected or reading fails.
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().map_or(false, |thread| !thread.is_finished())
+    }
+}
+
+impl Drop for SerialResponseBox {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
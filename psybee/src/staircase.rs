@@ -0,0 +1,784 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Adaptive psychophysical procedures. All procedures implement the
+//! `AdaptiveProcedure` trait and assume that higher intensities make the task
+//! easier, i.e. intensity is decreased after correct responses and increased
+//! after incorrect ones.
+//!
+//! All procedures (and `InterleavedStaircases`, which randomly interleaves
+//! several procedures using a seeded random number generator) implement
+//! `Serialize` and `Deserialize`, so their state can be saved and restored
+//! e.g. between sessions.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use psybee::staircase::{AdaptiveProcedure, StepType, UpDownOptions, UpDownStaircase};
+//!
+//! // a 1-up/3-down staircase on the contrast of a Gabor patch
+//! let mut staircase = UpDownStaircase::new(UpDownOptions { start_intensity: 0.5,
+//!                                                          n_up: 1,
+//!                                                          n_down: 3,
+//!                                                          step_sizes: vec![4.0, 2.0, 1.0],
+//!                                                          step_type: StepType::Db,
+//!                                                          ..Default::default() }).unwrap();
+//!
+//! while !staircase.is_finished() {
+//!     let contrast = staircase.next_intensity();
+//!     // ... set the contrast of the Gabor patch and run the trial ...
+//!     # let correct = true;
+//!     staircase.update(correct);
+//! }
+//!
+//! println!("Threshold: {:?}", staircase.threshold_estimate());
+//! ```
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::PsybeeError;
+
+/// Common interface of all adaptive procedures.
+pub trait AdaptiveProcedure {
+    /// Returns the stimulus intensity for the next trial.
+    fn next_intensity(&mut self) -> f64;
+    /// Updates the procedure with the response to the intensity that was last
+    /// returned by `next_intensity()`. `true` means the response was correct.
+    fn update(&mut self, response: bool);
+    /// Returns the current estimate of the threshold, or None if there is not
+    /// enough data yet.
+    fn threshold_estimate(&self) -> Option<f64>;
+    /// Returns true if the procedure's stopping criterion has been reached.
+    fn is_finished(&self) -> bool;
+    /// Returns all intensities and responses so far.
+    fn history(&self) -> &[TrialResponse];
+}
+
+/// The intensity of a trial and the response to it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrialResponse {
+    pub intensity: f64,
+    pub response: bool,
+}
+
+/// The scale steps are applied on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepType {
+    /// Steps are added to the intensity.
+    Linear,
+    /// Steps are added to the log10 of the intensity.
+    Log,
+    /// Steps are given in decibels (20 * log10 of the intensity).
+    Db,
+}
+
+impl StepType {
+    fn to_scale(self, intensity: f64) -> f64 {
+        match self {
+            StepType::Linear => intensity,
+            StepType::Log => intensity.log10(),
+            StepType::Db => 20.0 * intensity.log10(),
+        }
+    }
+
+    fn from_scale(self, value: f64) -> f64 {
+        match self {
+            StepType::Linear => value,
+            StepType::Log => 10f64.powf(value),
+            StepType::Db => 10f64.powf(value / 20.0),
+        }
+    }
+}
+
+/// Direction of a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Direction {
+    Up,
+    Down,
+}
+
+impl Direction {
+    fn sign(self) -> f64 {
+        match self {
+            Direction::Up => 1.0,
+            Direction::Down => -1.0,
+        }
+    }
+}
+
+/// Clamps the intensity to the optional bounds.
+fn clamp_intensity(intensity: f64, min: Option<f64>, max: Option<f64>) -> f64 {
+    let intensity = min.map_or(intensity, |min| intensity.max(min));
+    max.map_or(intensity, |max| intensity.min(max))
+}
+
+fn check_start_intensity(intensity: f64, step_type: StepType) -> Result<(), PsybeeError> {
+    if !intensity.is_finite() || (step_type != StepType::Linear && intensity <= 0.0) {
+        return Err(PsybeeError::InvalidStaircaseOptionsError(format!("Invalid start intensity {} for {:?} steps.",
+                                                                     intensity, step_type)));
+    }
+    Ok(())
+}
+
+/// Options for a transformed up/down staircase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpDownOptions {
+    /// Intensity of the first trial.
+    pub start_intensity: f64,
+    /// Number of consecutive incorrect responses after which the intensity is
+    /// increased.
+    pub n_up: u32,
+    /// Number of consecutive correct responses after which the intensity is
+    /// decreased. A 1-up/2-down staircase converges to 70.7% correct, a
+    /// 1-up/3-down staircase to 79.4% correct.
+    pub n_down: u32,
+    /// Step sizes. The first step size is used until the first reversal, the
+    /// second until the second reversal and so on. The last step size is used
+    /// for all remaining trials.
+    pub step_sizes: Vec<f64>,
+    /// The scale the step sizes are given in.
+    pub step_type: StepType,
+    /// Lower bound of the intensity.
+    pub min_intensity: Option<f64>,
+    /// Upper bound of the intensity.
+    pub max_intensity: Option<f64>,
+    /// Stop after this many reversals.
+    pub n_reversals: Option<usize>,
+    /// Stop after this many trials.
+    pub n_trials: Option<usize>,
+    /// Number of final reversals that are averaged to estimate the threshold.
+    /// If None, all reversals except the first are used.
+    pub n_estimate_reversals: Option<usize>,
+}
+
+impl Default for UpDownOptions {
+    fn default() -> Self {
+        Self { start_intensity: 1.0,
+               n_up: 1,
+               n_down: 2,
+               step_sizes: vec![0.1],
+               step_type: StepType::Linear,
+               min_intensity: None,
+               max_intensity: None,
+               n_reversals: Some(10),
+               n_trials: None,
+               n_estimate_reversals: Some(6) }
+    }
+}
+
+/// A transformed up/down staircase (Levitt, 1971).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpDownStaircase {
+    options: UpDownOptions,
+    intensity: f64,
+    n_correct_in_row: u32,
+    n_incorrect_in_row: u32,
+    last_direction: Option<Direction>,
+    reversals: Vec<f64>,
+    history: Vec<TrialResponse>,
+}
+
+impl UpDownStaircase {
+    /// Create a new staircase.
+    pub fn new(options: UpDownOptions) -> Result<Self, PsybeeError> {
+        if options.step_sizes.is_empty() {
+            return Err(PsybeeError::InvalidStaircaseOptionsError("At least one step size is required.".to_string()));
+        }
+        if options.n_up == 0 || options.n_down == 0 {
+            return Err(PsybeeError::InvalidStaircaseOptionsError("n_up and n_down must be at least 1.".to_string()));
+        }
+        check_start_intensity(options.start_intensity, options.step_type)?;
+
+        Ok(Self { intensity: options.start_intensity,
+                  options,
+                  n_correct_in_row: 0,
+                  n_incorrect_in_row: 0,
+                  last_direction: None,
+                  reversals: Vec::new(),
+                  history: Vec::new() })
+    }
+
+    /// Returns the intensities at which the staircase reversed.
+    pub fn reversals(&self) -> &[f64] {
+        &self.reversals
+    }
+
+    fn step(&mut self, direction: Direction) {
+        if self.last_direction.is_some_and(|last| last != direction) {
+            self.reversals.push(self.intensity);
+        }
+        self.last_direction = Some(direction);
+
+        let step_sizes = &self.options.step_sizes;
+        let step = step_sizes[self.reversals.len().min(step_sizes.len() - 1)];
+
+        let step_type = self.options.step_type;
+        let intensity = step_type.from_scale(step_type.to_scale(self.intensity) + direction.sign() * step);
+        self.intensity = clamp_intensity(intensity, self.options.min_intensity, self.options.max_intensity);
+    }
+}
+
+impl AdaptiveProcedure for UpDownStaircase {
+    fn next_intensity(&mut self) -> f64 {
+        self.intensity
+    }
+
+    fn update(&mut self, response: bool) {
+        self.history.push(TrialResponse { intensity: self.intensity,
+                                          response });
+
+        if response {
+            self.n_correct_in_row += 1;
+            self.n_incorrect_in_row = 0;
+            if self.n_correct_in_row >= self.options.n_down {
+                self.n_correct_in_row = 0;
+                self.step(Direction::Down);
+            }
+        } else {
+            self.n_incorrect_in_row += 1;
+            self.n_correct_in_row = 0;
+            if self.n_incorrect_in_row >= self.options.n_up {
+                self.n_incorrect_in_row = 0;
+                self.step(Direction::Up);
+            }
+        }
+    }
+
+    fn threshold_estimate(&self) -> Option<f64> {
+        let n = match self.options.n_estimate_reversals {
+            Some(n) => n.min(self.reversals.len()),
+            None => self.reversals.len().saturating_sub(1),
+        };
+        if n == 0 {
+            return None;
+        }
+
+        // average on the scale the steps are applied on
+        let step_type = self.options.step_type;
+        let mean = self.reversals[self.reversals.len() - n..].iter()
+                                                             .map(|&r| step_type.to_scale(r))
+                                                             .sum::<f64>()
+                   / n as f64;
+
+        Some(step_type.from_scale(mean))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.options.n_reversals.is_some_and(|n| self.reversals.len() >= n)
+        || self.options.n_trials.is_some_and(|n| self.history.len() >= n)
+    }
+
+    fn history(&self) -> &[TrialResponse] {
+        &self.history
+    }
+}
+
+/// Options for PEST.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PestOptions {
+    /// Intensity of the first trial.
+    pub start_intensity: f64,
+    /// Size of the first step.
+    pub start_step: f64,
+    /// The procedure stops once the step size falls below this value.
+    pub min_step: f64,
+    /// Largest allowed step size.
+    pub max_step: f64,
+    /// Proportion of correct responses the procedure converges to.
+    pub target_performance: f64,
+    /// Deviation (in number of correct responses) from the expected number of
+    /// correct responses at which the intensity is changed.
+    pub wald_constant: f64,
+    /// The scale the step sizes are given in.
+    pub step_type: StepType,
+    /// Lower bound of the intensity.
+    pub min_intensity: Option<f64>,
+    /// Upper bound of the intensity.
+    pub max_intensity: Option<f64>,
+    /// Stop after this many trials, even if the step size is still above
+    /// `min_step`.
+    pub n_trials: Option<usize>,
+}
+
+impl Default for PestOptions {
+    fn default() -> Self {
+        Self { start_intensity: 1.0,
+               start_step: 0.2,
+               min_step: 0.01,
+               max_step: 0.4,
+               target_performance: 0.75,
+               wald_constant: 1.0,
+               step_type: StepType::Linear,
+               min_intensity: None,
+               max_intensity: None,
+               n_trials: None }
+    }
+}
+
+/// Parameter Estimation by Sequential Testing (Taylor & Creelman, 1967).
+///
+/// Trials are run at the same intensity until a sequential test shows that
+/// performance deviates from the target performance. The step size is halved
+/// on every reversal and doubled after several steps in the same direction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pest {
+    options: PestOptions,
+    intensity: f64,
+    step: f64,
+    n_trials_at_level: u32,
+    n_correct_at_level: u32,
+    last_direction: Option<Direction>,
+    n_steps_in_direction: u32,
+    last_step_doubled: bool,
+    doubled_before_reversal: bool,
+    converged: bool,
+    history: Vec<TrialResponse>,
+}
+
+impl Pest {
+    /// Create a new PEST procedure.
+    pub fn new(options: PestOptions) -> Result<Self, PsybeeError> {
+        if !(options.target_performance > 0.0 && options.target_performance < 1.0) {
+            return Err(PsybeeError::InvalidStaircaseOptionsError("The target performance must be between 0 and 1.".to_string()));
+        }
+        if !(options.min_step > 0.0 && options.min_step <= options.start_step && options.start_step <= options.max_step) {
+            return Err(PsybeeError::InvalidStaircaseOptionsError("Step sizes must satisfy 0 < min_step <= start_step <= max_step.".to_string()));
+        }
+        check_start_intensity(options.start_intensity, options.step_type)?;
+
+        Ok(Self { intensity: options.start_intensity,
+                  step: options.start_step,
+                  options,
+                  n_trials_at_level: 0,
+                  n_correct_at_level: 0,
+                  last_direction: None,
+                  n_steps_in_direction: 0,
+                  last_step_doubled: false,
+                  doubled_before_reversal: false,
+                  converged: false,
+                  history: Vec::new() })
+    }
+
+    /// Returns the current step size.
+    pub fn step_size(&self) -> f64 {
+        self.step
+    }
+}
+
+impl AdaptiveProcedure for Pest {
+    fn next_intensity(&mut self) -> f64 {
+        self.intensity
+    }
+
+    fn update(&mut self, response: bool) {
+        self.history.push(TrialResponse { intensity: self.intensity,
+                                          response });
+
+        if self.converged {
+            return;
+        }
+
+        self.n_trials_at_level += 1;
+        self.n_correct_at_level += response as u32;
+
+        // Wald sequential test
+        let expected = self.options.target_performance * self.n_trials_at_level as f64;
+        let n_correct = self.n_correct_at_level as f64;
+        let direction = if n_correct > expected + self.options.wald_constant {
+            Direction::Down
+        } else if n_correct < expected - self.options.wald_constant {
+            Direction::Up
+        } else {
+            return;
+        };
+
+        // step size rules
+        match self.last_direction {
+            Some(last) if last != direction => {
+                // rule 1: halve the step on every reversal
+                self.step /= 2.0;
+                self.n_steps_in_direction = 1;
+                self.doubled_before_reversal = self.last_step_doubled;
+                self.last_step_doubled = false;
+
+                if self.step < self.options.min_step {
+                    self.converged = true;
+                    return;
+                }
+            }
+            Some(_) => {
+                self.n_steps_in_direction += 1;
+                // rule 2: the second step in the same direction has the same size
+                // rule 3: the fourth and all further steps are doubled
+                // rule 4: the third step is doubled unless the step before the last reversal
+                // was doubled
+                let double = self.n_steps_in_direction >= 4 || (self.n_steps_in_direction == 3 && !self.doubled_before_reversal);
+                if double {
+                    self.step = (self.step * 2.0).min(self.options.max_step);
+                }
+                self.last_step_doubled = double;
+            }
+            None => {
+                self.n_steps_in_direction = 1;
+            }
+        }
+        self.last_direction = Some(direction);
+
+        let step_type = self.options.step_type;
+        let intensity = step_type.from_scale(step_type.to_scale(self.intensity) + direction.sign() * self.step);
+        self.intensity = clamp_intensity(intensity, self.options.min_intensity, self.options.max_intensity);
+
+        self.n_trials_at_level = 0;
+        self.n_correct_at_level = 0;
+    }
+
+    fn threshold_estimate(&self) -> Option<f64> {
+        // the threshold estimate is the final intensity
+        (!self.history.is_empty()).then_some(self.intensity)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.converged || self.options.n_trials.is_some_and(|n| self.history.len() >= n)
+    }
+
+    fn history(&self) -> &[TrialResponse] {
+        &self.history
+    }
+}
+
+/// How QUEST chooses the intensity of the next trial from the posterior
+/// distribution of the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum QuestMethod {
+    /// Mean of the posterior (King-Smith et al., 1994).
+    Mean,
+    /// Mode of the posterior (Watson & Pelli, 1983).
+    Mode,
+    /// The given quantile of the posterior.
+    Quantile(f64),
+}
+
+/// Options for QUEST. QUEST assumes a Weibull psychometric function on a log10
+/// intensity scale.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestOptions {
+    /// Prior guess of the threshold (must be positive).
+    pub threshold_guess: f64,
+    /// Standard deviation of the prior in log10 units.
+    pub threshold_guess_sd: f64,
+    /// Proportion of correct responses at threshold.
+    pub p_threshold: f64,
+    /// Slope of the psychometric function.
+    pub beta: f64,
+    /// Lapse rate, i.e. the proportion of trials on which the observer
+    /// responds blindly.
+    pub delta: f64,
+    /// Guess rate, i.e. the proportion of correct responses at very low
+    /// intensities (0.5 for 2AFC).
+    pub gamma: f64,
+    /// Resolution of the posterior in log10 units.
+    pub grain: f64,
+    /// Range of the posterior (centred on the guess) in log10 units.
+    pub range: f64,
+    /// How the next intensity is chosen.
+    pub method: QuestMethod,
+    /// Lower bound of the intensity.
+    pub min_intensity: Option<f64>,
+    /// Upper bound of the intensity.
+    pub max_intensity: Option<f64>,
+    /// Stop after this many trials.
+    pub n_trials: Option<usize>,
+}
+
+impl Default for QuestOptions {
+    fn default() -> Self {
+        Self { threshold_guess: 0.1,
+               threshold_guess_sd: 1.0,
+               p_threshold: 0.82,
+               beta: 3.5,
+               delta: 0.01,
+               gamma: 0.5,
+               grain: 0.01,
+               range: 5.0,
+               method: QuestMethod::Mean,
+               min_intensity: None,
+               max_intensity: None,
+               n_trials: Some(40) }
+    }
+}
+
+/// QUEST, a Bayesian adaptive procedure (Watson & Pelli, 1983).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quest {
+    options: QuestOptions,
+    /// Offset that makes the psychometric function pass through
+    /// `p_threshold` at the threshold.
+    x_threshold: f64,
+    /// Candidate thresholds (log10 units).
+    thresholds: Vec<f64>,
+    /// Log posterior density of each candidate threshold (unnormalised).
+    log_posterior: Vec<f64>,
+    intensity: f64,
+    history: Vec<TrialResponse>,
+}
+
+impl Quest {
+    /// Create a new QUEST procedure.
+    pub fn new(options: QuestOptions) -> Result<Self, PsybeeError> {
+        if !(options.threshold_guess > 0.0) || !(options.threshold_guess_sd > 0.0) {
+            return Err(PsybeeError::InvalidStaircaseOptionsError("The threshold guess and its standard deviation must be positive.".to_string()));
+        }
+        if !(options.grain > 0.0 && options.range > options.grain) {
+            return Err(PsybeeError::InvalidStaircaseOptionsError("The grain must be positive and smaller than the range.".to_string()));
+        }
+        if !(0.0..1.0).contains(&options.gamma) || !(0.0..1.0).contains(&options.delta) {
+            return Err(PsybeeError::InvalidStaircaseOptionsError("gamma and delta must be between 0 and 1.".to_string()));
+        }
+
+        let q = (options.p_threshold - options.delta * options.gamma) / (1.0 - options.delta);
+        if !(q > options.gamma && q < 1.0) {
+            return Err(PsybeeError::InvalidStaircaseOptionsError(format!("p_threshold {} is not reachable with gamma {} and delta {}.",
+                                                                         options.p_threshold, options.gamma, options.delta)));
+        }
+        let x_threshold = ((1.0 - options.gamma) / (1.0 - q)).ln().log10() / options.beta;
+
+        let guess = options.threshold_guess.log10();
+        let n_half = (options.range / options.grain / 2.0).round() as i64;
+        let thresholds = (-n_half..=n_half).map(|i| guess + i as f64 * options.grain).collect::<Vec<_>>();
+        let log_posterior = thresholds.iter()
+                                      .map(|t| -0.5 * ((t - guess) / options.threshold_guess_sd).powi(2))
+                                      .collect();
+
+        let mut quest = Self { options,
+                               x_threshold,
+                               thresholds,
+                               log_posterior,
+                               intensity: 0.0,
+                               history: Vec::new() };
+        quest.intensity = quest.compute_intensity();
+
+        Ok(quest)
+    }
+
+    /// Probability of a correct response at `x` log10 units above the
+    /// threshold.
+    fn p_correct(&self, x: f64) -> f64 {
+        let QuestOptions { beta, delta, gamma, .. } = self.options;
+        delta * gamma + (1.0 - delta) * (1.0 - (1.0 - gamma) * (-(10f64.powf(beta * (x + self.x_threshold)))).exp())
+    }
+
+    /// Returns the normalised posterior.
+    fn posterior(&self) -> Vec<f64> {
+        let max = self.log_posterior.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let pdf = self.log_posterior.iter().map(|l| (l - max).exp()).collect::<Vec<_>>();
+        let sum = pdf.iter().sum::<f64>();
+        pdf.into_iter().map(|p| p / sum).collect()
+    }
+
+    /// Returns the estimate of the threshold (log10 units) using the given
+    /// method.
+    fn estimate(&self, method: QuestMethod) -> f64 {
+        let pdf = self.posterior();
+
+        match method {
+            QuestMethod::Mean => self.thresholds.iter().zip(&pdf).map(|(t, p)| t * p).sum(),
+            QuestMethod::Mode => {
+                let (i, _) = pdf.iter()
+                                .enumerate()
+                                .fold((0, f64::NEG_INFINITY), |best, (i, &p)| if p > best.1 { (i, p) } else { best });
+                self.thresholds[i]
+            }
+            QuestMethod::Quantile(quantile) => {
+                let mut cumulative = 0.0;
+                for (t, p) in self.thresholds.iter().zip(&pdf) {
+                    cumulative += p;
+                    if cumulative >= quantile {
+                        return *t;
+                    }
+                }
+                *self.thresholds.last().unwrap()
+            }
+        }
+    }
+
+    fn compute_intensity(&self) -> f64 {
+        let intensity = 10f64.powf(self.estimate(self.options.method));
+        clamp_intensity(intensity, self.options.min_intensity, self.options.max_intensity)
+    }
+
+    /// Returns the standard deviation of the posterior in log10 units.
+    pub fn threshold_sd(&self) -> f64 {
+        let mean = self.estimate(QuestMethod::Mean);
+        self.thresholds
+            .iter()
+            .zip(self.posterior())
+            .map(|(t, p)| p * (t - mean).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+impl AdaptiveProcedure for Quest {
+    fn next_intensity(&mut self) -> f64 {
+        self.intensity
+    }
+
+    fn update(&mut self, response: bool) {
+        self.history.push(TrialResponse { intensity: self.intensity,
+                                          response });
+
+        let x = self.intensity.log10();
+        for i in 0..self.thresholds.len() {
+            let p = self.p_correct(x - self.thresholds[i]);
+            let likelihood = if response { p } else { 1.0 - p };
+            self.log_posterior[i] += likelihood.max(f64::MIN_POSITIVE).ln();
+        }
+
+        self.intensity = self.compute_intensity();
+    }
+
+    fn threshold_estimate(&self) -> Option<f64> {
+        Some(10f64.powf(self.estimate(QuestMethod::Mean)))
+    }
+
+    fn is_finished(&self) -> bool {
+        self.options.n_trials.is_some_and(|n| self.history.len() >= n)
+    }
+
+    fn history(&self) -> &[TrialResponse] {
+        &self.history
+    }
+}
+
+/// Any of the adaptive procedures in this module. Useful to store different
+/// kinds of procedures together, e.g. in `InterleavedStaircases`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Staircase {
+    UpDown(UpDownStaircase),
+    Pest(Pest),
+    Quest(Quest),
+}
+
+impl Staircase {
+    fn inner(&self) -> &dyn AdaptiveProcedure {
+        match self {
+            Staircase::UpDown(s) => s,
+            Staircase::Pest(s) => s,
+            Staircase::Quest(s) => s,
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn AdaptiveProcedure {
+        match self {
+            Staircase::UpDown(s) => s,
+            Staircase::Pest(s) => s,
+            Staircase::Quest(s) => s,
+        }
+    }
+}
+
+impl AdaptiveProcedure for Staircase {
+    fn next_intensity(&mut self) -> f64 {
+        self.inner_mut().next_intensity()
+    }
+
+    fn update(&mut self, response: bool) {
+        self.inner_mut().update(response)
+    }
+
+    fn threshold_estimate(&self) -> Option<f64> {
+        self.inner().threshold_estimate()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.inner().is_finished()
+    }
+
+    fn history(&self) -> &[TrialResponse] {
+        self.inner().history()
+    }
+}
+
+impl From<UpDownStaircase> for Staircase {
+    fn from(staircase: UpDownStaircase) -> Self {
+        Staircase::UpDown(staircase)
+    }
+}
+
+impl From<Pest> for Staircase {
+    fn from(staircase: Pest) -> Self {
+        Staircase::Pest(staircase)
+    }
+}
+
+impl From<Quest> for Staircase {
+    fn from(staircase: Quest) -> Self {
+        Staircase::Quest(staircase)
+    }
+}
+
+/// Randomly interleaves several adaptive procedures. On every trial, one of
+/// the procedures that have not finished yet is chosen at random. The random
+/// number generator is seeded, so the order is reproducible (and restored
+/// when the state is deserialised).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterleavedStaircases<P = Staircase> {
+    procedures: Vec<P>,
+    rng: ChaCha8Rng,
+    current: Option<usize>,
+}
+
+impl<P: AdaptiveProcedure> InterleavedStaircases<P> {
+    /// Create a new set of interleaved procedures.
+    pub fn new(procedures: Vec<P>, seed: u64) -> Result<Self, PsybeeError> {
+        if procedures.is_empty() {
+            return Err(PsybeeError::EmptyVectorError);
+        }
+
+        Ok(Self { procedures,
+                  rng: ChaCha8Rng::seed_from_u64(seed),
+                  current: None })
+    }
+
+    /// Chooses the procedure for the next trial and returns its index and the
+    /// intensity. Returns None once all procedures have finished.
+    pub fn next_intensity(&mut self) -> Option<(usize, f64)> {
+        let active = (0..self.procedures.len()).filter(|&i| !self.procedures[i].is_finished())
+                                               .collect::<Vec<_>>();
+        if active.is_empty() {
+            self.current = None;
+            return None;
+        }
+
+        let index = active[self.rng.gen_range(0..active.len())];
+        self.current = Some(index);
+
+        Some((index, self.procedures[index].next_intensity()))
+    }
+
+    /// Updates the procedure that was chosen by the last call to
+    /// `next_intensity()`.
+    pub fn update(&mut self, response: bool) -> Result<(), PsybeeError> {
+        let Some(index) = self.current.take() else {
+            return Err(PsybeeError::CustomError("No trial is in progress. Call next_intensity() first.".to_string()));
+        };
+
+        self.procedures[index].update(response);
+        Ok(())
+    }
+
+    /// Returns true if all procedures have finished.
+    pub fn is_finished(&self) -> bool {
+        self.procedures.iter().all(|p| p.is_finished())
+    }
+
+    /// Returns the interleaved procedures.
+    pub fn procedures(&self) -> &[P] {
+        &self.procedures
+    }
+}
@@ -0,0 +1,348 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Trial and block structure. A `TrialHandler` takes a list of conditions,
+//! expands them into a sequence of trials (with repetitions and blocks),
+//! optionally randomises their order, and logs the outcome of each trial.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use psybee::trials::{load_conditions, TrialHandler, TrialOptions, TrialOrder};
+//!
+//! let conditions = load_conditions("conditions.csv", b',').unwrap();
+//! let options = TrialOptions { n_repetitions: 2,
+//!                              n_blocks: 3,
+//!                              order: TrialOrder::Random,
+//!                              seed: Some(42) };
+//!
+//! let mut trials = TrialHandler::new(conditions, options).unwrap()
+//!                                                        .with_logger("trials.csv", vec!["response", "rt"], false)
+//!                                                        .unwrap();
+//!
+//! while let Some(trial) = trials.next_trial() {
+//!     let contrast: f64 = trial.condition.get_parsed("contrast").unwrap();
+//!     // ... run the trial ...
+//!     trials.record(("left", 0.512)).unwrap();
+//! }
+//! ```
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::errors::PsybeeError;
+use crate::utils::{CSVEventLogger, IntoStringVector};
+
+/// A condition that can be used with a `TrialHandler`. All conditions of a
+/// `TrialHandler` must have the same column names.
+pub trait Condition: Clone {
+    /// The names of the columns that describe the condition.
+    fn column_names(&self) -> Vec<String>;
+    /// The values of the columns that describe the condition, in the same
+    /// order as `column_names()`.
+    fn column_values(&self) -> Vec<String>;
+}
+
+/// A condition loaded from a CSV file, i.e. a single row with named columns.
+#[derive(Debug, Clone)]
+pub struct ConditionRow {
+    columns: Arc<Vec<String>>,
+    values: Vec<String>,
+}
+
+impl ConditionRow {
+    /// Returns the value of the given column, or None if the column does not
+    /// exist.
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.columns
+            .iter()
+            .position(|c| c == column)
+            .map(|i| self.values[i].as_str())
+    }
+
+    /// Returns the value of the given column, parsed into the requested type.
+    pub fn get_parsed<T>(&self, column: &str) -> Result<T, PsybeeError>
+        where T: FromStr,
+              T::Err: std::fmt::Display
+    {
+        let value = self.get(column)
+                        .ok_or_else(|| PsybeeError::ColumnNameDoesNotExistError(column.to_string()))?;
+
+        value.parse()
+             .map_err(|e| PsybeeError::CustomError(format!("Failed to parse value \"{}\" of column {}: {}", value, column, e)))
+    }
+}
+
+impl Condition for ConditionRow {
+    fn column_names(&self) -> Vec<String> {
+        self.columns.to_vec()
+    }
+
+    fn column_values(&self) -> Vec<String> {
+        self.values.clone()
+    }
+}
+
+/// Loads a list of conditions from a CSV file with a header row. Use `b'\t'`
+/// as the delimiter for TSV files.
+pub fn load_conditions<P>(path: P, delimiter: u8) -> Result<Vec<ConditionRow>, PsybeeError>
+    where P: AsRef<std::path::Path>
+{
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_path(path)?;
+
+    let columns: Vec<String> = reader.headers()?.iter().map(|s| s.to_string()).collect();
+    let columns = Arc::new(columns);
+
+    reader.records()
+          .map(|record| {
+              Ok(ConditionRow { columns: columns.clone(),
+                                values: record?.iter().map(|s| s.to_string()).collect() })
+          })
+          .collect()
+}
+
+/// The order in which trials are presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrialOrder {
+    /// Conditions are presented in the given order.
+    Sequential,
+    /// All trials within a block are shuffled.
+    Random,
+    /// Each repetition of the conditions is shuffled separately, so that every
+    /// condition occurs once before any condition is repeated.
+    RandomWithinRepetitions,
+}
+
+/// Options for a `TrialHandler`.
+#[derive(Debug, Clone)]
+pub struct TrialOptions {
+    /// How often each condition is presented within a block.
+    pub n_repetitions: u32,
+    /// The number of blocks. Each block contains every condition
+    /// `n_repetitions` times.
+    pub n_blocks: u32,
+    /// The order in which trials are presented.
+    pub order: TrialOrder,
+    /// Seed for the random number generator. If None, a random seed is
+    /// chosen (see `TrialHandler::seed()`).
+    pub seed: Option<u64>,
+}
+
+impl Default for TrialOptions {
+    fn default() -> Self {
+        Self { n_repetitions: 1,
+               n_blocks: 1,
+               order: TrialOrder::Random,
+               seed: None }
+    }
+}
+
+/// A single trial.
+#[derive(Debug, Clone)]
+pub struct Trial<C> {
+    /// Index of the trial within the whole experiment (starting at 0).
+    pub index: usize,
+    /// Index of the block (starting at 0).
+    pub block: u32,
+    /// Index of the trial within the block (starting at 0).
+    pub index_in_block: usize,
+    /// Index of the repetition of the condition (starting at 0).
+    pub repetition: u32,
+    /// Index of the condition in the list of conditions.
+    pub condition_index: usize,
+    /// The condition.
+    pub condition: C,
+}
+
+/// Columns that are logged for every trial, in addition to the condition and
+/// outcome columns.
+const TRIAL_COLUMNS: [&str; 5] = ["trial", "block", "trial_in_block", "repetition", "condition"];
+
+/// Runs through a sequence of trials. See the module documentation for an
+/// example.
+pub struct TrialHandler<C: Condition> {
+    trials: Vec<Trial<C>>,
+    /// Index of the next trial.
+    next: usize,
+    /// True if the experiment has been aborted.
+    aborted: bool,
+    seed: u64,
+    logger: Option<CSVEventLogger>,
+    n_outcome_columns: usize,
+}
+
+impl<C: Condition> TrialHandler<C> {
+    /// Create a new trial handler from a list of conditions. Returns an error
+    /// if there are no conditions or no blocks or repetitions, as there would
+    /// be no trials to run.
+    pub fn new(conditions: Vec<C>, options: TrialOptions) -> Result<Self, PsybeeError> {
+        if conditions.is_empty() {
+            return Err(PsybeeError::EmptyVectorError);
+        }
+        if options.n_blocks == 0 || options.n_repetitions == 0 {
+            return Err(PsybeeError::InvalidTrialOptionsError("n_blocks and n_repetitions must be at least 1".to_string()));
+        }
+
+        let seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+
+        let mut trials = Vec::new();
+
+        for block in 0..options.n_blocks {
+            // (repetition, condition index) pairs of this block
+            let mut block_trials = Vec::new();
+            for repetition in 0..options.n_repetitions {
+                let mut repetition_trials = (0..conditions.len()).map(|i| (repetition, i)).collect::<Vec<_>>();
+
+                if options.order == TrialOrder::RandomWithinRepetitions {
+                    repetition_trials.shuffle(&mut rng);
+                }
+
+                block_trials.extend(repetition_trials);
+            }
+
+            if options.order == TrialOrder::Random {
+                block_trials.shuffle(&mut rng);
+            }
+
+            for (index_in_block, (repetition, condition_index)) in block_trials.into_iter().enumerate() {
+                trials.push(Trial { index: trials.len(),
+                                    block,
+                                    index_in_block,
+                                    repetition,
+                                    condition_index,
+                                    condition: conditions[condition_index].clone() });
+            }
+        }
+
+        Ok(Self { trials,
+                  next: 0,
+                  aborted: false,
+                  seed,
+                  logger: None,
+                  n_outcome_columns: 0 })
+    }
+
+    /// Log the outcome of each trial to a CSV file. Besides the given outcome
+    /// columns, the file contains the trial, block, repetition and condition
+    /// indices as well as all condition columns.
+    pub fn with_logger<P, I, S>(mut self, path: P, outcome_columns: I, overwrite: bool) -> Result<Self, PsybeeError>
+        where P: Into<std::path::PathBuf>,
+              I: IntoIterator<Item = S>,
+              S: Into<String> + 'static
+    {
+        let outcome_columns: Vec<String> = outcome_columns.into_iter().map(Into::into).collect();
+        let condition_columns = self.trials[0].condition.column_names();
+
+        let columns: Vec<String> = TRIAL_COLUMNS.iter()
+                                                .map(|c| c.to_string())
+                                                .chain(condition_columns)
+                                                .chain(outcome_columns.iter().cloned())
+                                                .collect();
+
+        self.logger = Some(CSVEventLogger::new(path, columns, b',', overwrite)?);
+        self.n_outcome_columns = outcome_columns.len();
+
+        Ok(self)
+    }
+
+    /// Advances to the next trial and returns it. Returns None once all trials
+    /// have been run or the experiment has been aborted.
+    pub fn next_trial(&mut self) -> Option<Trial<C>> {
+        if self.aborted || self.next >= self.trials.len() {
+            return None;
+        }
+
+        self.next += 1;
+        Some(self.trials[self.next - 1].clone())
+    }
+
+    /// Returns the current trial, i.e. the trial last returned by
+    /// `next_trial()`.
+    pub fn current_trial(&self) -> Option<&Trial<C>> {
+        self.next.checked_sub(1).map(|i| &self.trials[i])
+    }
+
+    /// Records the outcome of the current trial. The values must match the
+    /// outcome columns passed to `with_logger()`. Does nothing if no logger
+    /// has been set.
+    pub fn record<I>(&mut self, outcome: I) -> Result<(), PsybeeError>
+        where I: IntoStringVector
+    {
+        let Some(logger) = self.logger.as_mut() else {
+            return Ok(());
+        };
+
+        let Some(trial) = self.next.checked_sub(1).map(|i| &self.trials[i]) else {
+            return Err(PsybeeError::CustomError("Cannot record an outcome before the first trial has started.".to_string()));
+        };
+
+        let outcome = outcome.into_string_vec();
+        if outcome.len() != self.n_outcome_columns {
+            return Err(PsybeeError::DataLengthMismatchError(outcome.len(), self.n_outcome_columns));
+        }
+
+        let values: Vec<String> = vec![trial.index.to_string(),
+                                       trial.block.to_string(),
+                                       trial.index_in_block.to_string(),
+                                       trial.repetition.to_string(),
+                                       trial.condition_index.to_string()].into_iter()
+                                                                         .chain(trial.condition.column_values())
+                                                                         .chain(outcome)
+                                                                         .collect();
+
+        logger.log(values)
+    }
+
+    /// Aborts the experiment. `next_trial()` will return None from now on.
+    pub fn abort(&mut self) {
+        self.aborted = true;
+    }
+
+    /// Skips the remaining trials of the current block.
+    pub fn end_block(&mut self) {
+        if let Some(block) = self.current_trial().map(|trial| trial.block) {
+            while self.next < self.trials.len() && self.trials[self.next].block == block {
+                self.next += 1;
+            }
+        }
+    }
+
+    /// Returns true if the experiment has been aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted
+    }
+
+    /// Returns true if all trials have been run or the experiment has been
+    /// aborted.
+    pub fn is_finished(&self) -> bool {
+        self.aborted || self.next >= self.trials.len()
+    }
+
+    /// Returns the total number of trials.
+    pub fn n_trials(&self) -> usize {
+        self.trials.len()
+    }
+
+    /// Returns the number of trials that have not been started yet.
+    pub fn n_remaining(&self) -> usize {
+        self.trials.len() - self.next
+    }
+
+    /// Returns the seed that was used to randomise the trial order. Log this to
+    /// be able to reproduce the order later.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns all trials in the order they will be presented.
+    pub fn trials(&self) -> &[Trial<C>] {
+        &self.trials
+    }
+}
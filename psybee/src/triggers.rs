@@ -0,0 +1,238 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Trigger outputs. Event codes (e.g. for EEG/MEG recordings) are sent through
+//! the `TriggerOutput` trait, so the same experiment code can use a parallel
+//! port, a serial trigger box (`serial::TriggerPort`, requires the `serial`
+//! feature), or a stand-in when no hardware is connected.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # fn run() -> Result<(), psybee::errors::PsybeeError> {
+//! use psybee::triggers::{LoggingTrigger, TriggerOutput};
+//!
+//! #[cfg(target_os = "linux")]
+//! let mut trigger: Box<dyn TriggerOutput> = Box::new(psybee::triggers::ParallelPort::new("/dev/parport0")?);
+//! #[cfg(not(target_os = "linux"))]
+//! let mut trigger: Box<dyn TriggerOutput> = Box::new(LoggingTrigger::new());
+//!
+//! // set the data lines to 42, and reset them to 0 after the pulse width
+//! trigger.send(42)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use crate::clock::Timestamp;
+use crate::errors::PsybeeError;
+
+/// The default pulse width of trigger outputs that reset automatically.
+pub const DEFAULT_PULSE_WIDTH: Duration = Duration::from_millis(5);
+
+/// A trigger code that was sent for a presented frame (see
+/// `Frame::add_trigger()` and `Window::sent_triggers()`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentTrigger {
+    /// The index of the frame the code was added to.
+    pub frame_index: u64,
+    /// The code.
+    pub code: u8,
+    /// The flip time of the frame.
+    pub flip_time: Timestamp,
+    /// The time the code was written to the output, or None if no output was
+    /// set (see `Window::set_trigger_output()`) or writing failed.
+    pub write_time: Option<Timestamp>,
+}
+
+/// A device that event codes can be sent to.
+pub trait TriggerOutput: Send + std::fmt::Debug {
+    /// Set the output to the given code and return the time it was written.
+    /// The code stays on the output until the next code is written.
+    fn write(&mut self, code: u8) -> Result<Timestamp, PsybeeError>;
+
+    /// The duration after which `send()` resets the output to 0, or None if
+    /// the output is not reset automatically. Frame triggers (see
+    /// `Frame::add_trigger()`) are reset after the same duration, but without
+    /// blocking the render task.
+    fn pulse_width(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Send a trigger with the given code and return the time it was
+    /// written. If the output resets automatically (see `pulse_width()`),
+    /// this blocks for the pulse width and then resets the output to 0.
+    fn send(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        let timestamp = self.write(code)?;
+
+        if let Some(pulse_width) = self.pulse_width() {
+            std::thread::sleep(pulse_width);
+            self.write(0)?;
+        }
+
+        Ok(timestamp)
+    }
+}
+
+impl<T: TriggerOutput + ?Sized> TriggerOutput for Box<T> {
+    fn write(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        (**self).write(code)
+    }
+
+    fn pulse_width(&self) -> Option<Duration> {
+        (**self).pulse_width()
+    }
+
+    fn send(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        (**self).send(code)
+    }
+}
+
+/// A trigger output that discards all codes.
+#[derive(Debug, Clone, Default)]
+pub struct NoopTrigger;
+
+impl TriggerOutput for NoopTrigger {
+    fn write(&mut self, _code: u8) -> Result<Timestamp, PsybeeError> {
+        Ok(Timestamp::now())
+    }
+}
+
+/// A trigger output that logs codes (using the `log` crate) and keeps a
+/// record of them instead of sending them to a device. Useful for testing
+/// experiments without the hardware.
+#[derive(Debug, Clone, Default)]
+pub struct LoggingTrigger {
+    pulse_width: Option<Duration>,
+    codes: Vec<(Timestamp, u8)>,
+}
+
+impl LoggingTrigger {
+    /// Create a new logging trigger that does not reset automatically.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the duration after which `send()` resets the output to 0, or None
+    /// to disable the automatic reset.
+    pub fn set_pulse_width(&mut self, pulse_width: Option<Duration>) {
+        self.pulse_width = pulse_width;
+    }
+
+    /// Returns all codes that were written so far, with the time they were
+    /// written.
+    pub fn codes(&self) -> &[(Timestamp, u8)] {
+        &self.codes
+    }
+}
+
+impl TriggerOutput for LoggingTrigger {
+    fn write(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        let timestamp = Timestamp::now();
+        log::info!("Trigger {} written", code);
+        self.codes.push((timestamp, code));
+        Ok(timestamp)
+    }
+
+    fn pulse_width(&self) -> Option<Duration> {
+        self.pulse_width
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use parallel::ParallelPort;
+
+#[cfg(target_os = "linux")]
+mod parallel {
+    use std::fs::{File, OpenOptions};
+    use std::os::fd::AsRawFd;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use super::{TriggerOutput, DEFAULT_PULSE_WIDTH};
+    use crate::clock::Timestamp;
+    use crate::errors::PsybeeError;
+
+    // ppdev ioctl requests (see linux/ppdev.h)
+    const PPCLAIM: u64 = 0x708b;
+    const PPRELEASE: u64 = 0x708c;
+    const PPEXCL: u64 = 0x708f;
+    const PPWDATA: u64 = 0x4001_7086;
+
+    /// A parallel (LPT) port, accessed through the ppdev driver
+    /// (`/dev/parport*`). The code is written to the eight data lines. The
+    /// user needs read and write access to the device, which usually means
+    /// being in the `lp` group.
+    #[derive(Debug)]
+    pub struct ParallelPort {
+        path: PathBuf,
+        file: File,
+        pulse_width: Option<Duration>,
+    }
+
+    impl ParallelPort {
+        /// Open and claim the parallel port at the given path (e.g.
+        /// `/dev/parport0`). By default, `send()` resets the data lines to 0
+        /// after `DEFAULT_PULSE_WIDTH`.
+        pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, PsybeeError> {
+            let path = path.as_ref().to_path_buf();
+            let file = OpenOptions::new().read(true).write(true).open(&path)?;
+
+            // request exclusive access, so no other driver writes to the port
+            ioctl(&file, PPEXCL, std::ptr::null_mut())?;
+            ioctl(&file, PPCLAIM, std::ptr::null_mut())?;
+
+            let mut port = Self { path,
+                                  file,
+                                  pulse_width: Some(DEFAULT_PULSE_WIDTH) };
+            port.write(0)?;
+
+            Ok(port)
+        }
+
+        /// Set the duration after which `send()` resets the data lines to 0,
+        /// or None to disable the automatic reset.
+        pub fn set_pulse_width(&mut self, pulse_width: Option<Duration>) {
+            self.pulse_width = pulse_width;
+        }
+
+        /// Returns the path of the port.
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl TriggerOutput for ParallelPort {
+        fn write(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+            let mut data = code;
+            ioctl(&self.file, PPWDATA, &mut data as *mut u8 as *mut libc::c_void)?;
+            Ok(Timestamp::now())
+        }
+
+        fn pulse_width(&self) -> Option<Duration> {
+            self.pulse_width
+        }
+    }
+
+    impl Drop for ParallelPort {
+        fn drop(&mut self) {
+            let _ = self.write(0);
+            let _ = ioctl(&self.file, PPRELEASE, std::ptr::null_mut());
+        }
+    }
+
+    fn ioctl(file: &File, request: u64, arg: *mut libc::c_void) -> Result<(), PsybeeError> {
+        // SAFETY: the file descriptor is valid for the lifetime of `file`, and `arg`
+        // is either null or points to the single byte expected by PPWDATA
+        let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, arg) };
+
+        if result < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+}
@@ -59,6 +59,7 @@ impl AtomicExt<bool> for std::sync::atomic::AtomicBool {
 
 pub use web_time as time;
 
+use crate::clock::{Clock, Timestamp};
 use crate::errors::{self, PsybeeError};
 
 /// Nonblocking logging. When using the `pyo3-log` crate, logging from a
@@ -240,6 +241,15 @@ impl CSVEventLogger {
         Ok(())
     }
 
+    /// Flush all buffered events to disk. Events are already flushed after
+    /// each call to `log()`, but this can be used in a shutdown hook (see
+    /// `ExperimentManager::add_shutdown_hook`) to make sure the file is
+    /// complete.
+    pub fn flush(&mut self) -> Result<(), PsybeeError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
     pub fn log_cols<I, J>(&mut self,
                           column_names: I,
                           column_values: J)
@@ -288,11 +298,14 @@ impl CSVEventLogger {
 
 pub struct BIDSEventLogger {
     logger: CSVEventLogger,
-    start_time: std::time::Instant,
+    clock: Clock,
 }
 
 impl BIDSEventLogger {
-    pub fn new<P, I, S>(path: P, columns: I, overwrite: bool) -> Result<Self, PsybeeError>
+    /// Create a new BIDSEventLogger that measures onsets on the given clock,
+    /// which should be the experiment clock (see `ExperimentManager::clock()`),
+    /// so that onsets line up with the timestamps of frames and input events.
+    pub fn new<P, I, S>(path: P, columns: I, overwrite: bool, clock: Clock) -> Result<Self, PsybeeError>
         where P: Into<std::path::PathBuf>,
               I: IntoIterator<Item = S>,
               S: Into<String> + 'static
@@ -318,19 +331,26 @@ impl BIDSEventLogger {
 
         let logger = CSVEventLogger::new(path, columns, '\t' as u8, overwrite)?;
 
-        Ok(Self { logger,
-                  start_time: std::time::Instant::now() })
+        Ok(Self { logger, clock })
     }
 
-    /// Log an event.
+    /// Log an event that occurs now.
     pub fn log<I>(&mut self, columns_values: I, duration: f64) -> Result<(), PsybeeError>
         where I: IntoStringVector
+    {
+        self.log_at(columns_values, Timestamp::now(), duration)
+    }
+
+    /// Log an event that occurred at the given time, e.g. the onset of a frame
+    /// (see `PresentationInfo::onset()`) or the timestamp of an input event.
+    pub fn log_at<I>(&mut self, columns_values: I, time: Timestamp, duration: f64) -> Result<(), PsybeeError>
+        where I: IntoStringVector
     {
         // convert to vector
         let columns_values: Vec<String> = columns_values.into_string_vec();
 
         // calculate onset and duration
-        let onset = self.start_time.elapsed().as_secs_f64();
+        let onset = self.clock.secs(time);
 
         // add onset and duration to event
         let columns_values: Vec<String> =
@@ -356,7 +376,7 @@ impl BIDSEventLogger {
         let column_values: Vec<String> = column_values.into_string_vec();
 
         // calculate onset and duration
-        let onset = self.start_time.elapsed().as_secs_f64();
+        let onset = self.clock.elapsed_secs();
 
         // add onset and duration to event
         let column_names: Vec<String> =
@@ -372,6 +392,11 @@ impl BIDSEventLogger {
         // log event
         self.logger.log_cols(column_names, column_values)
     }
+
+    /// Flush all buffered events to disk (see `CSVEventLogger::flush`).
+    pub fn flush(&mut self) -> Result<(), PsybeeError> {
+        self.logger.flush()
+    }
 }
 
 pub fn sleep_secs(secs: f64) {
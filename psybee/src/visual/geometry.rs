@@ -1,6 +1,6 @@
 //! This module contains structs and traits that are used to specify the
-//! geometry of a stimulus. This includes rectangles, circles, and
-//! transformations.
+//! geometry of a stimulus. This includes shapes (rectangles, circles,
+//! ellipses, annuli, polygons, lines and crosses) and transformations.
 
 use nalgebra::{Matrix2, Matrix3};
 
@@ -275,7 +275,19 @@ pub trait ToVertices: Send + Sync {
         self.to_vertices_px(1.0, 1.0, 1, 1).len()
     }
 
-    fn contains(&self, window: &Window, trans: &Transformation2D, x: Size, y: Size) -> bool;
+    /// Returns true if the point lies within the shape after the
+    /// transformation has been applied. The default implementation maps the
+    /// point into the coordinate system of the untransformed shape and tests
+    /// it against the triangles returned by `to_vertices_px()`, so it works
+    /// for any transformation that can be inverted.
+    fn contains(&self, window: &Window, trans: &Transformation2D, x: Size, y: Size) -> bool {
+        let Some(point) = local_point_px(window, trans, x, y) else {
+            return false;
+        };
+
+        let vertices = self.to_vertices_px(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px());
+        triangles_contain(&vertices, point)
+    }
 }
 
 impl ToVertices for Box<dyn ToVertices> {
@@ -412,35 +424,6 @@ impl ToVertices for Rectangle {
     fn clone_box(&self) -> Box<dyn ToVertices> {
         Box::new(self.clone())
     }
-
-    fn contains(&self, window: &Window, trans: &Transformation2D, x: Size, y: Size) -> bool {
-        let px = x.to_pixels(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px());
-
-        let py = y.to_pixels(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px());
-
-        // apply the transformation to left, top, width, and height
-        let trans_mat = trans.to_transformation_matrix(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px());
-
-        let x1 = self.left
-                     .to_pixels(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px()) as f32;
-        let y1 = self.top
-                     .to_pixels(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px()) as f32;
-        let x2 = x1
-                 + self.width
-                       .to_pixels(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px()) as f32;
-        let y2 = y1
-                 + self.height
-                       .to_pixels(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px()) as f32;
-
-        // apply the transformation matrix to the rectangle
-        let a = (trans_mat * nalgebra::Vector3::new(x1, y1, 1.0)).xy();
-        let b = (trans_mat * nalgebra::Vector3::new(x2, y1, 1.0)).xy();
-
-        let p = nalgebra::Vector2::new(px as f32, py as f32);
-
-        // check if the point is inside the transformed rectangle
-        a.x <= p.x && p.x <= b.x && a.y <= p.y && p.y <= b.y
-    }
 }
 
 impl ToVertices for Circle {
@@ -483,32 +466,600 @@ impl ToVertices for Circle {
     fn clone_box(&self) -> Box<dyn ToVertices> {
         Box::new(self.clone())
     }
+}
+
+/// Number of segments used to approximate the outline of curved shapes.
+const N_SEGMENTS: usize = 500;
+
+/// Maximum length of a mitred join of a `Polyline`, as a multiple of half
+/// its thickness. Sharper corners are bevelled instead, i.e. cut off where the
+/// outlines of the two segments end.
+const MITER_LIMIT: f64 = 4.0;
+
+/// An ellipse with a given center and radii.
+#[derive(Clone)]
+pub struct Ellipse {
+    pub center_x: Size,
+    pub center_y: Size,
+    pub radius_x: Size,
+    pub radius_y: Size,
+}
+
+/// A ring between two concentric circles.
+#[derive(Clone)]
+pub struct Annulus {
+    pub center_x: Size,
+    pub center_y: Size,
+    pub inner_radius: Size,
+    pub outer_radius: Size,
+}
+
+/// A polygon with the given vertices. The polygon may be concave, but its
+/// edges must not intersect.
+#[derive(Clone)]
+pub struct Polygon {
+    pub points: Vec<SizeVector2D>,
+}
+
+/// A straight line between two points with a given thickness.
+#[derive(Clone)]
+pub struct Line {
+    pub x1: Size,
+    pub y1: Size,
+    pub x2: Size,
+    pub y2: Size,
+    pub thickness: Size,
+}
+
+/// A sequence of connected straight lines with a given thickness. Lines are
+/// joined with mitred corners, except for very sharp corners, which are
+/// bevelled.
+#[derive(Clone)]
+pub struct Polyline {
+    pub points: Vec<SizeVector2D>,
+    pub thickness: Size,
+}
+
+/// A cross (e.g. a fixation cross) with a horizontal and a vertical bar of
+/// the same length and thickness.
+#[derive(Clone)]
+pub struct Cross {
+    pub center_x: Size,
+    pub center_y: Size,
+    /// The length of each bar.
+    pub size: Size,
+    /// The thickness of each bar.
+    pub thickness: Size,
+}
+
+impl Ellipse {
+    /// Create a new ellipse.
+    ///
+    /// # Arguments
+    ///
+    /// * `center_x` - The x coordinate of the center of the ellipse.
+    /// * `center_y` - The y coordinate of the center of the ellipse.
+    /// * `radius_x` - The horizontal radius of the ellipse.
+    /// * `radius_y` - The vertical radius of the ellipse.
+    ///
+    /// # Returns
+    ///
+    /// A new ellipse.
+    pub fn new(center_x: impl Into<Size>, center_y: impl Into<Size>, radius_x: impl Into<Size>, radius_y: impl Into<Size>) -> Self {
+        Self { center_x: center_x.into(),
+               center_y: center_y.into(),
+               radius_x: radius_x.into(),
+               radius_y: radius_y.into() }
+    }
+}
+
+impl Annulus {
+    /// Create a new annulus.
+    ///
+    /// # Arguments
+    ///
+    /// * `center_x` - The x coordinate of the center of the annulus.
+    /// * `center_y` - The y coordinate of the center of the annulus.
+    /// * `inner_radius` - The radius of the hole.
+    /// * `outer_radius` - The outer radius of the annulus.
+    ///
+    /// # Returns
+    ///
+    /// A new annulus.
+    pub fn new(center_x: impl Into<Size>, center_y: impl Into<Size>, inner_radius: impl Into<Size>, outer_radius: impl Into<Size>) -> Self {
+        Self { center_x: center_x.into(),
+               center_y: center_y.into(),
+               inner_radius: inner_radius.into(),
+               outer_radius: outer_radius.into() }
+    }
+}
+
+impl Polygon {
+    /// Create a new polygon from its vertices, given in order (clockwise or
+    /// counter-clockwise).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use psybee::visual::geometry::Polygon;
+    ///
+    /// // an arrow pointing to the right
+    /// let arrow = Polygon::new(vec![(-50.0, -10.0), (0.0, -10.0), (0.0, -30.0), (50.0, 0.0), (0.0, 30.0), (0.0, 10.0), (-50.0, 10.0)]);
+    /// ```
+    pub fn new<I, P>(points: I) -> Self
+        where I: IntoIterator<Item = P>,
+              P: Into<SizeVector2D>
+    {
+        Self { points: points.into_iter().map(Into::into).collect() }
+    }
+}
+
+impl Line {
+    /// Create a new line.
+    ///
+    /// # Arguments
+    ///
+    /// * `x1`, `y1` - The start point of the line.
+    /// * `x2`, `y2` - The end point of the line.
+    /// * `thickness` - The thickness of the line.
+    ///
+    /// # Returns
+    ///
+    /// A new line.
+    pub fn new(x1: impl Into<Size>, y1: impl Into<Size>, x2: impl Into<Size>, y2: impl Into<Size>, thickness: impl Into<Size>) -> Self {
+        Self { x1: x1.into(),
+               y1: y1.into(),
+               x2: x2.into(),
+               y2: y2.into(),
+               thickness: thickness.into() }
+    }
+}
+
+impl Polyline {
+    /// Create a new polyline through the given points.
+    pub fn new<I, P>(points: I, thickness: impl Into<Size>) -> Self
+        where I: IntoIterator<Item = P>,
+              P: Into<SizeVector2D>
+    {
+        Self { points: points.into_iter().map(Into::into).collect(),
+               thickness: thickness.into() }
+    }
+}
+
+impl Cross {
+    /// Create a new cross.
+    ///
+    /// # Arguments
+    ///
+    /// * `center_x` - The x coordinate of the center of the cross.
+    /// * `center_y` - The y coordinate of the center of the cross.
+    /// * `size` - The length of each bar.
+    /// * `thickness` - The thickness of each bar.
+    ///
+    /// # Returns
+    ///
+    /// A new cross.
+    pub fn new(center_x: impl Into<Size>, center_y: impl Into<Size>, size: impl Into<Size>, thickness: impl Into<Size>) -> Self {
+        Self { center_x: center_x.into(),
+               center_y: center_y.into(),
+               size: size.into(),
+               thickness: thickness.into() }
+    }
+}
+
+impl ToVertices for Ellipse {
+    fn to_vertices_px(&self, screenwidth_mm: f64, viewing_distance_mm: f64, width_px: u32, height_px: u32) -> Vec<Vertex> {
+        let center_x = self.center_x.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+        let center_y = self.center_y.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+        let radius_x = self.radius_x.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+        let radius_y = self.radius_y.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+
+        let outline: Vec<(f64, f64)> = (0..N_SEGMENTS).map(|i| {
+                                                          let theta = 2.0 * std::f64::consts::PI * (i as f64 / N_SEGMENTS as f64);
+                                                          (center_x + radius_x * theta.cos(), center_y + radius_y * theta.sin())
+                                                      })
+                                                      .collect();
+
+        let mut triangles = Vec::with_capacity(N_SEGMENTS * 3);
+        for i in 0..N_SEGMENTS {
+            triangles.extend([(center_x, center_y), outline[i], outline[(i + 1) % N_SEGMENTS]]);
+        }
+
+        textured_vertices(&triangles)
+    }
+
+    fn clone_box(&self) -> Box<dyn ToVertices> {
+        Box::new(self.clone())
+    }
+
+    fn contains(&self, window: &Window, trans: &Transformation2D, x: Size, y: Size) -> bool {
+        let Some((x, y)) = local_point_px(window, trans, x, y) else {
+            return false;
+        };
+
+        let center_x = window_px(window, &self.center_x);
+        let center_y = window_px(window, &self.center_y);
+        let radius_x = window_px(window, &self.radius_x);
+        let radius_y = window_px(window, &self.radius_y);
+
+        ((x - center_x) / radius_x).powi(2) + ((y - center_y) / radius_y).powi(2) <= 1.0
+    }
+}
+
+impl ToVertices for Annulus {
+    fn to_vertices_px(&self, screenwidth_mm: f64, viewing_distance_mm: f64, width_px: u32, height_px: u32) -> Vec<Vertex> {
+        let center_x = self.center_x.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+        let center_y = self.center_y.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+        let inner_radius = self.inner_radius.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+        let outer_radius = self.outer_radius.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+
+        let point = |radius: f64, i: usize| {
+            let theta = 2.0 * std::f64::consts::PI * (i as f64 / N_SEGMENTS as f64);
+            (center_x + radius * theta.cos(), center_y + radius * theta.sin())
+        };
+
+        // each segment of the ring is a quad between the inner and the outer circle
+        let mut triangles = Vec::with_capacity(N_SEGMENTS * 6);
+        for i in 0..N_SEGMENTS {
+            let (inner, outer) = (point(inner_radius, i), point(outer_radius, i));
+            let (next_inner, next_outer) = (point(inner_radius, i + 1), point(outer_radius, i + 1));
+            triangles.extend([inner, outer, next_outer, inner, next_outer, next_inner]);
+        }
+
+        textured_vertices(&triangles)
+    }
+
+    fn clone_box(&self) -> Box<dyn ToVertices> {
+        Box::new(self.clone())
+    }
 
     fn contains(&self, window: &Window, trans: &Transformation2D, x: Size, y: Size) -> bool {
-        let physical_width = window.physical_width();
-        let viewing_distance = window.viewing_distance();
-        let width_px = window.width_px();
-        let height_px = window.height_px();
+        let Some((x, y)) = local_point_px(window, trans, x, y) else {
+            return false;
+        };
 
-        let trans_mat = trans.to_transformation_matrix(physical_width, viewing_distance, width_px, height_px).transpose();
+        let center_x = window_px(window, &self.center_x);
+        let center_y = window_px(window, &self.center_y);
+        let inner_radius = window_px(window, &self.inner_radius);
+        let outer_radius = window_px(window, &self.outer_radius);
 
-        let inv_mat = trans_mat.try_inverse().expect("Could not invert transformation matrix");
+        let distance = (x - center_x).hypot(y - center_y);
+        inner_radius <= distance && distance <= outer_radius
+    }
+}
 
-        let p = nalgebra::Point2::new(x.to_pixels(physical_width, viewing_distance, width_px, height_px) as f32,
-                                      y.to_pixels(physical_width, viewing_distance, width_px, height_px) as f32);
+impl ToVertices for Polygon {
+    fn to_vertices_px(&self, screenwidth_mm: f64, viewing_distance_mm: f64, width_px: u32, height_px: u32) -> Vec<Vertex> {
+        let points: Vec<(f64, f64)> = self.points
+                                          .iter()
+                                          .map(|p| p.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px))
+                                          .collect();
 
-        let center = (trans_mat
-                      * nalgebra::Vector3::new(self.center_x.to_pixels(physical_width, viewing_distance, width_px, height_px) as f32,
-                                               self.center_y.to_pixels(physical_width, viewing_distance, width_px, height_px) as f32,
-                                               1.0)).xy()
-                                                    .into();
+        let triangles: Vec<(f64, f64)> = triangulate(&points).into_iter().flatten().map(|i| points[i]).collect();
 
-        let radius = self.radius.to_pixels(physical_width, viewing_distance, width_px, height_px) as f32;
+        textured_vertices(&triangles)
+    }
 
-        nalgebra::distance(&center, &p) <= radius
+    fn clone_box(&self) -> Box<dyn ToVertices> {
+        Box::new(self.clone())
+    }
+
+    fn contains(&self, window: &Window, trans: &Transformation2D, x: Size, y: Size) -> bool {
+        let Some((x, y)) = local_point_px(window, trans, x, y) else {
+            return false;
+        };
+
+        let points: Vec<(f64, f64)> = self.points
+                                          .iter()
+                                          .map(|p| p.to_pixels(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px()))
+                                          .collect();
+
+        // even-odd rule: the point is inside if a ray from it crosses the outline an odd
+        // number of times
+        let mut inside = false;
+        for i in 0..points.len() {
+            let (x1, y1) = points[i];
+            let (x2, y2) = points[(i + 1) % points.len()];
+
+            if (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1) {
+                inside = !inside;
+            }
+        }
+        inside
     }
 }
 
+impl ToVertices for Line {
+    fn to_vertices_px(&self, screenwidth_mm: f64, viewing_distance_mm: f64, width_px: u32, height_px: u32) -> Vec<Vertex> {
+        let start = (self.x1.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px),
+                     self.y1.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px));
+        let end = (self.x2.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px),
+                   self.y2.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px));
+        let thickness = self.thickness.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+
+        textured_vertices(&stroke(&[start, end], thickness))
+    }
+
+    fn clone_box(&self) -> Box<dyn ToVertices> {
+        Box::new(self.clone())
+    }
+
+    fn contains(&self, window: &Window, trans: &Transformation2D, x: Size, y: Size) -> bool {
+        let Some((x, y)) = local_point_px(window, trans, x, y) else {
+            return false;
+        };
+
+        let (x1, y1) = (window_px(window, &self.x1), window_px(window, &self.y1));
+        let (x2, y2) = (window_px(window, &self.x2), window_px(window, &self.y2));
+        let thickness = window_px(window, &self.thickness);
+
+        // position of the point along the line and its distance from the line
+        let length = (x2 - x1).hypot(y2 - y1);
+        if length == 0.0 {
+            return false;
+        }
+        let along = ((x - x1) * (x2 - x1) + (y - y1) * (y2 - y1)) / length;
+        let across = ((x - x1) * (y2 - y1) - (y - y1) * (x2 - x1)) / length;
+
+        (0.0..=length).contains(&along) && across.abs() <= thickness / 2.0
+    }
+}
+
+impl ToVertices for Polyline {
+    fn to_vertices_px(&self, screenwidth_mm: f64, viewing_distance_mm: f64, width_px: u32, height_px: u32) -> Vec<Vertex> {
+        let points: Vec<(f64, f64)> = self.points
+                                          .iter()
+                                          .map(|p| p.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px))
+                                          .collect();
+        let thickness = self.thickness.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+
+        textured_vertices(&stroke(&points, thickness))
+    }
+
+    fn clone_box(&self) -> Box<dyn ToVertices> {
+        Box::new(self.clone())
+    }
+}
+
+impl ToVertices for Cross {
+    fn to_vertices_px(&self, screenwidth_mm: f64, viewing_distance_mm: f64, width_px: u32, height_px: u32) -> Vec<Vertex> {
+        let center_x = self.center_x.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+        let center_y = self.center_y.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px);
+        let half_size = self.size.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px) / 2.0;
+        let half_thickness = self.thickness.to_pixels(screenwidth_mm, viewing_distance_mm, width_px, height_px) / 2.0;
+
+        let rectangle = |left: f64, right: f64, bottom: f64, top: f64| {
+            [(left, bottom), (right, bottom), (right, top), (left, bottom), (right, top), (left, top)]
+        };
+
+        // the pieces must not overlap, as the centre would otherwise be drawn twice
+        // (and look darker with semi-transparent colours)
+        let (left, right) = (center_x - half_size, center_x + half_size);
+        let (bar_left, bar_right) = (center_x - half_thickness, center_x + half_thickness);
+        let (bar_bottom, bar_top) = (center_y - half_thickness, center_y + half_thickness);
+
+        let mut triangles = Vec::with_capacity(18);
+        // the horizontal bar ...
+        triangles.extend(rectangle(left, right, bar_bottom, bar_top));
+        // ... and the parts of the vertical bar below and above it
+        triangles.extend(rectangle(bar_left, bar_right, center_y - half_size, bar_bottom));
+        triangles.extend(rectangle(bar_left, bar_right, bar_top, center_y + half_size));
+
+        textured_vertices(&triangles)
+    }
+
+    fn clone_box(&self) -> Box<dyn ToVertices> {
+        Box::new(self.clone())
+    }
+
+    fn contains(&self, window: &Window, trans: &Transformation2D, x: Size, y: Size) -> bool {
+        let Some((x, y)) = local_point_px(window, trans, x, y) else {
+            return false;
+        };
+
+        let dx = (x - window_px(window, &self.center_x)).abs();
+        let dy = (y - window_px(window, &self.center_y)).abs();
+        let half_size = window_px(window, &self.size) / 2.0;
+        let half_thickness = window_px(window, &self.thickness) / 2.0;
+
+        (dx <= half_size && dy <= half_thickness) || (dx <= half_thickness && dy <= half_size)
+    }
+}
+
+/// Converts a size to pixels for the given window.
+fn window_px(window: &Window, size: &Size) -> f64 {
+    size.to_pixels(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px())
+}
+
+/// Converts a point to pixels and maps it into the coordinate system of the
+/// untransformed shape, i.e. applies the inverse of the transformation.
+/// Returns None if the transformation cannot be inverted (e.g. when scaling
+/// by 0).
+fn local_point_px(window: &Window, trans: &Transformation2D, x: Size, y: Size) -> Option<(f64, f64)> {
+    // transformation matrices are applied to row vectors, so we transpose them to
+    // apply them to column vectors (as when rendering)
+    let trans_mat = trans.to_transformation_matrix(window.physical_width(), window.viewing_distance(), window.width_px(), window.height_px())
+                         .map(|v| v as f64)
+                         .transpose();
+    let inv_mat = trans_mat.try_inverse()?;
+
+    let p = inv_mat * nalgebra::Vector3::new(window_px(window, &x), window_px(window, &y), 1.0);
+    Some((p.x, p.y))
+}
+
+/// Creates vertices from a list of triangles (three points each, in pixels).
+/// Texture coordinates are based on the bounding box of all points.
+fn textured_vertices(triangles: &[(f64, f64)]) -> Vec<Vertex> {
+    let (min_x, max_x) = triangles.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| (min.min(p.0), max.max(p.0)));
+    let (min_y, max_y) = triangles.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| (min.min(p.1), max.max(p.1)));
+
+    let relative = |value: f64, min: f64, max: f64| if max > min { ((value - min) / (max - min)) as f32 } else { 0.5 };
+
+    triangles.iter()
+             .map(|&(x, y)| {
+                 Vertex { position: [x as f32, y as f32, 0.0],
+                          color: [1.0, 1.0, 1.0],
+                          tex_coords: [relative(x, min_x, max_x), 1.0 - relative(y, min_y, max_y)] }
+             })
+             .collect()
+}
+
+/// Returns true if the point lies within any of the triangles formed by the
+/// vertices. Degenerate triangles (e.g. the joins of mitred corners, see
+/// `stroke()`) do not contain any points.
+fn triangles_contain(vertices: &[Vertex], (x, y): (f64, f64)) -> bool {
+    vertices.chunks_exact(3).any(|triangle| {
+                                let [a, b, c] = [0, 1, 2].map(|i| (triangle[i].position[0] as f64, triangle[i].position[1] as f64));
+                                cross(a, b, c) != 0.0 && triangle_contains(a, b, c, (x, y))
+                            })
+}
+
+/// Returns true if the point lies within (or on the edge of) the triangle.
+fn triangle_contains(a: (f64, f64), b: (f64, f64), c: (f64, f64), p: (f64, f64)) -> bool {
+    let d1 = cross(a, b, p);
+    let d2 = cross(b, c, p);
+    let d3 = cross(c, a, p);
+
+    let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+
+    !(has_negative && has_positive)
+}
+
+/// The z component of the cross product of `b - a` and `c - a`, i.e. positive
+/// if `a`, `b`, `c` are in counter-clockwise order.
+fn cross(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+/// Triangulates a simple (possibly concave) polygon using ear clipping.
+/// Returns the indices of the vertices of each triangle. A polygon with n
+/// vertices always results in n - 2 triangles, so the number of vertices of a
+/// shape does not depend on its size.
+fn triangulate(points: &[(f64, f64)]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return vec![];
+    }
+
+    // make sure the vertices are in counter-clockwise order
+    let signed_area: f64 = (0..points.len()).map(|i| {
+                                                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                                                a.0 * b.1 - b.0 * a.1
+                                            })
+                                            .sum();
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if signed_area < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+
+        // an ear is a convex corner whose triangle contains no other vertex
+        let ear = (0..n).find(|&i| {
+                            let (prev, current, next) = (remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]);
+                            let (a, b, c) = (points[prev], points[current], points[next]);
+
+                            cross(a, b, c) > 0.0
+                            && remaining.iter()
+                                        .filter(|&&j| j != prev && j != current && j != next)
+                                        .all(|&j| points[j] == a || points[j] == b || points[j] == c || !triangle_contains(a, b, c, points[j]))
+                        });
+
+        // degenerate polygons (e.g. with all vertices on a line) have no ears, in which
+        // case we cut off the first corner
+        let i = ear.unwrap_or(1);
+
+        triangles.push([remaining[(i + n - 1) % n], remaining[i], remaining[(i + 1) % n]]);
+        remaining.remove(i);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+/// Creates the triangles of a line with the given thickness through the
+/// points. Consecutive segments are joined with mitred corners, or with
+/// bevelled ones if the miter would be longer than `MITER_LIMIT`.
+fn stroke(points: &[(f64, f64)], thickness: f64) -> Vec<(f64, f64)> {
+    if points.len() < 2 {
+        return vec![];
+    }
+
+    let half = thickness / 2.0;
+
+    // the unit normal of each segment
+    let normals: Vec<(f64, f64)> = points.windows(2)
+                                         .map(|segment| {
+                                             let (dx, dy) = (segment[1].0 - segment[0].0, segment[1].1 - segment[0].1);
+                                             let length = dx.hypot(dy);
+                                             if length > 0.0 {
+                                                 (-dy / length, dx / length)
+                                             } else {
+                                                 (0.0, 0.0)
+                                             }
+                                         })
+                                         .collect();
+
+    // the offset of the outline at the start and the end of each segment, which is
+    // perpendicular to the segment unless it is mitred with its neighbour
+    let mut start_offsets: Vec<(f64, f64)> = normals.iter().map(|n| (n.0 * half, n.1 * half)).collect();
+    let mut end_offsets = start_offsets.clone();
+
+    // every corner gets a join triangle (which is degenerate unless the corner is
+    // bevelled), so the number of vertices does not depend on the shape of the line
+    let mut joins = Vec::with_capacity(points.len().saturating_sub(2) * 3);
+
+    for i in 1..points.len() - 1 {
+        let (before, after) = (normals[i - 1], normals[i]);
+        let p = points[i];
+
+        let miter = (before.0 + after.0, before.1 + after.1);
+        let miter_length = miter.0.hypot(miter.1);
+        if miter_length < 1e-9 {
+            // the line doubles back on itself, so both segments cover the corner
+            joins.extend([p, p, p]);
+            continue;
+        }
+
+        let miter = (miter.0 / miter_length, miter.1 / miter_length);
+        let cos = miter.0 * after.0 + miter.1 * after.1;
+
+        if cos * MITER_LIMIT >= 1.0 {
+            let length = half / cos;
+            end_offsets[i - 1] = (miter.0 * length, miter.1 * length);
+            start_offsets[i] = end_offsets[i - 1];
+            joins.extend([p, p, p]);
+        } else {
+            // bevel: both segments keep their full width and the gap on the outside of
+            // the corner is filled with a triangle (the outside is on the right for
+            // left turns and vice versa)
+            let side = if before.0 * after.1 - before.1 * after.0 > 0.0 { -1.0 } else { 1.0 };
+            joins.extend([p,
+                          (p.0 + side * before.0 * half, p.1 + side * before.1 * half),
+                          (p.0 + side * after.0 * half, p.1 + side * after.1 * half)]);
+        }
+    }
+
+    let mut triangles = Vec::with_capacity((points.len() - 1) * 6 + joins.len());
+    for i in 0..points.len() - 1 {
+        let (p, q) = (points[i], points[i + 1]);
+        let (p_offset, q_offset) = (start_offsets[i], end_offsets[i]);
+
+        let p_left = (p.0 + p_offset.0, p.1 + p_offset.1);
+        let p_right = (p.0 - p_offset.0, p.1 - p_offset.1);
+        let q_left = (q.0 + q_offset.0, q.1 + q_offset.1);
+        let q_right = (q.0 - q_offset.0, q.1 - q_offset.1);
+
+        triangles.extend([p_left, p_right, q_right, p_left, q_right, q_left]);
+    }
+    triangles.extend(joins);
+
+    triangles
+}
+
 /// 2D transformations that can be applied to a stimulus.
 /// This enum is used to specify the transformation of a stimulus. The
 /// transformation is applied to the object just before it is rendered.
@@ -29,8 +29,10 @@
 //! a fixation cross and a grating stimulus.
 pub mod color;
 pub mod geometry;
+pub mod photodiode;
 // pub mod stimuli;
 pub mod stimuli;
+pub mod timing;
 pub mod window;
 
 use async_trait::async_trait;
@@ -0,0 +1,82 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Photodiode markers. A photodiode marker is a small square in one of the
+//! corners of the window that changes its colour on marked frames (see
+//! `Frame::mark()`). A photodiode taped to the screen can then be used to
+//! verify the onset of stimuli, e.g. in EEG experiments.
+
+use super::color::RawRgba;
+use super::geometry::{Rectangle, Size};
+use super::stimuli::ColorStimulus;
+use super::Window;
+
+/// A corner of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Options for the photodiode marker.
+#[derive(Debug, Clone)]
+pub struct PhotodiodeMarkerOptions {
+    /// The corner of the window the marker is drawn in.
+    pub corner: Corner,
+    /// The width and height of the marker.
+    pub size: Size,
+    /// The colour of the marker on marked frames.
+    pub on_color: RawRgba,
+    /// The colour of the marker on all other frames.
+    pub off_color: RawRgba,
+}
+
+impl Default for PhotodiodeMarkerOptions {
+    fn default() -> Self {
+        Self { corner: Corner::BottomLeft,
+               size: Size::Pixels(50.0),
+               on_color: RawRgba::new(1.0, 1.0, 1.0, 1.0),
+               off_color: RawRgba::new(0.0, 0.0, 0.0, 1.0) }
+    }
+}
+
+/// The photodiode marker of a window.
+#[derive(Debug, Clone)]
+pub(crate) struct PhotodiodeMarker {
+    pub(crate) options: PhotodiodeMarkerOptions,
+    pub(crate) stimulus: ColorStimulus,
+}
+
+impl PhotodiodeMarker {
+    pub(crate) fn new(window: &Window, options: PhotodiodeMarkerOptions) -> Self {
+        let size = options.size.clone();
+
+        // the origin is in the center of the window and y points up
+        let left = match options.corner {
+            Corner::TopLeft | Corner::BottomLeft => Size::ScreenWidth(-0.5),
+            Corner::TopRight | Corner::BottomRight => Size::ScreenWidth(0.5) - size.clone(),
+        };
+        let bottom = match options.corner {
+            Corner::BottomLeft | Corner::BottomRight => Size::ScreenHeight(-0.5),
+            Corner::TopLeft | Corner::TopRight => Size::ScreenHeight(0.5) - size.clone(),
+        };
+
+        let stimulus = ColorStimulus::new(window, Rectangle::new(left, bottom, size.clone(), size), options.off_color);
+
+        Self { options, stimulus }
+    }
+
+    /// Sets the colour of the marker depending on whether the frame is marked.
+    pub(crate) fn set_marked(&self, marked: bool) {
+        if marked {
+            self.stimulus.set_color(self.options.on_color);
+        } else {
+            self.stimulus.set_color(self.options.off_color);
+        }
+    }
+}
@@ -129,14 +129,15 @@ impl FillPattern for Sprite {
         return None;
     }
 
-    fn uniform_buffer_data(&mut self, _window: &Window) -> Option<Vec<u8>> {
+    fn uniform_buffer_data(&mut self, window: &Window) -> Option<Vec<u8>> {
 
         // if fps is set, calculate the index based on the time
         let mut index = self.current_index;
         if let Some(fps) = self.fps {
-            let elapsed = self.init_time.elapsed().as_secs_f64();
-            let frames = elapsed * fps;
-            index = frames as u64;
+            // round the elapsed time to whole refresh cycles, so that images change at
+            // consistent frames instead of jittering around frame boundaries
+            let elapsed = window.frames_to_secs(window.secs_to_frames(self.init_time.elapsed().as_secs_f64()));
+            index = image_index(elapsed, fps);
         }
 
         if let Some(repeat) = self.repeat {
@@ -186,3 +187,48 @@ impl FillPattern for Sprite {
         ".to_string()
     }
 }
+
+/// Returns the index of the image that is shown `elapsed` seconds after the
+/// sprite was created when playing at `fps` images per second. Values that are
+/// within floating point error of an image change (e.g. 2/60 s at 30 fps) are
+/// rounded to it, so the change is not delayed by a frame.
+fn image_index(elapsed: f64, fps: f64) -> u64 {
+    let images = elapsed * fps;
+    let nearest = images.round();
+
+    if (images - nearest).abs() < 1e-6 {
+        nearest.max(0.0) as u64
+    } else {
+        images.floor().max(0.0) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_changes_on_the_refresh_cycle_it_is_due() {
+        for refresh_rate in [60u64, 75, 120, 144] {
+            for fps in [1u64, 3, 7, 10, 15, 20, 24, 30, 60] {
+                for n_frames in 0..1000 {
+                    let elapsed = n_frames as f64 / refresh_rate as f64;
+                    assert_eq!(image_index(elapsed, fps as f64),
+                               n_frames * fps / refresh_rate,
+                               "{} frames at {} Hz and {} fps",
+                               n_frames,
+                               refresh_rate,
+                               fps);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn image_index_truncates_partial_images() {
+        assert_eq!(image_index(0.0, 30.0), 0);
+        assert_eq!(image_index(0.049, 10.0), 0);
+        assert_eq!(image_index(0.15, 10.0), 1);
+        assert_eq!(image_index(0.999, 1.0), 0);
+    }
+}
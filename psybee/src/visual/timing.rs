@@ -0,0 +1,463 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Frame timing. This module contains the information that is returned when a
+//! frame is presented, and the helpers that are used to estimate when a frame
+//! was flipped to the screen.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::clock::Timestamp;
+
+/// Information about the presentation of a single frame.
+#[derive(Debug, Clone, Copy)]
+pub struct PresentationInfo {
+    /// Index of the frame, counting all frames that have been presented on the
+    /// window (starting at 0).
+    pub frame_index: u64,
+    /// Time at which the frame was submitted, i.e. when `Window::present()`
+    /// was called.
+    pub submit_time: Instant,
+    /// (Estimated) time at which the frame was flipped to the screen, according
+    /// to the `TimestampingStrategy`. None if the frame has not been presented
+    /// yet, e.g. when using `BlockingStrategy::DoNotBlock`.
+    pub flip_time: Option<Instant>,
+    /// True if the frame was presented later than it should have been, i.e. at
+    /// least one vertical blank was missed.
+    pub missed_frame: bool,
+    /// Number of vertical blanks that were missed before this frame was
+    /// presented.
+    pub missed_vblanks: u32,
+}
+
+impl PresentationInfo {
+    /// Returns the onset of the frame, i.e. its flip time if available and its
+    /// submit time otherwise.
+    pub fn onset(&self) -> Timestamp {
+        self.flip_time.unwrap_or(self.submit_time).into()
+    }
+}
+
+/// Summary of the frames that have been presented on a window.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    /// Number of frames that have been presented.
+    pub n_frames: u64,
+    /// Number of frames that were presented late.
+    pub n_missed_frames: u64,
+    /// Total number of vertical blanks that were missed.
+    pub n_missed_vblanks: u64,
+    /// Refresh interval the flip intervals are compared against.
+    pub refresh_interval: Duration,
+    /// Mean interval between consecutive flips.
+    pub mean_flip_interval: Duration,
+    /// Standard deviation of the intervals between consecutive flips.
+    pub sd_flip_interval: Duration,
+    /// Shortest interval between consecutive flips.
+    pub min_flip_interval: Duration,
+    /// Longest interval between consecutive flips.
+    pub max_flip_interval: Duration,
+}
+
+/// Result of measuring the refresh rate of a window (see
+/// `Window::measure_refresh_rate()`).
+#[derive(Debug, Clone, Copy)]
+pub struct RefreshRateMeasurement {
+    /// Measured refresh rate in Hz, based on the mean flip interval.
+    pub refresh_rate: f64,
+    /// Refresh rate reported by the operating system in Hz.
+    pub nominal_refresh_rate: f64,
+    /// Number of flip intervals that were measured.
+    pub n_intervals: usize,
+    /// Mean flip interval (excluding outliers).
+    pub mean_interval: Duration,
+    /// Standard deviation of the flip intervals (excluding outliers).
+    pub sd_interval: Duration,
+    /// Number of intervals that deviated by more than 20% from the median
+    /// interval, e.g. because a frame was dropped.
+    pub n_outliers: usize,
+}
+
+impl RefreshRateMeasurement {
+    /// Computes the measurement from a list of flip intervals (in seconds).
+    /// Returns None if there are no valid intervals.
+    pub(crate) fn from_intervals(intervals: &[f64], nominal_refresh_rate: f64) -> Option<Self> {
+        let mut sorted = intervals.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = *sorted.get(sorted.len() / 2)?;
+
+        let inliers = intervals.iter()
+                               .copied()
+                               .filter(|interval| (interval - median).abs() <= 0.2 * median)
+                               .collect::<Vec<_>>();
+
+        let n = inliers.len() as f64;
+        let mean = inliers.iter().sum::<f64>() / n;
+        let sd = (inliers.iter().map(|interval| (interval - mean).powi(2)).sum::<f64>() / n).sqrt();
+
+        if mean.is_nan() || mean <= 0.0 {
+            return None;
+        }
+
+        Some(Self { refresh_rate: 1.0 / mean,
+                    nominal_refresh_rate,
+                    n_intervals: intervals.len(),
+                    mean_interval: Duration::from_secs_f64(mean),
+                    sd_interval: Duration::from_secs_f64(sd),
+                    n_outliers: intervals.len() - inliers.len() })
+    }
+}
+
+/// Identifier of a callback that is called for every presented frame.
+pub type FrameCallbackId = usize;
+/// A callback that is called for every presented frame.
+pub(crate) type FrameCallback = Box<dyn Fn(&PresentationInfo) + Send + Sync>;
+
+/// Detects dropped frames by comparing the intervals between flips against
+/// the refresh interval (see `FrameDropCheckStrategy::Timing`).
+///
+/// Each frame is expected to be flipped at the first vertical blank after it
+/// was received by the render task (but not before the vertical blank after
+/// the previous flip). If the actual flip happens later, the vertical blanks in
+/// between were missed.
+#[derive(Debug, Clone)]
+pub(crate) struct FrameDropDetector {
+    refresh_interval: f64,
+    last_flip: Option<Instant>,
+    stats: FrameStats,
+    // running sums of the flip intervals (in seconds)
+    n_intervals: u64,
+    sum_intervals: f64,
+    sum_sq_intervals: f64,
+}
+
+impl FrameDropDetector {
+    /// Create a new detector with the given refresh interval.
+    pub(crate) fn new(refresh_interval: Duration) -> Self {
+        Self { refresh_interval: refresh_interval.as_secs_f64(),
+               last_flip: None,
+               stats: FrameStats { refresh_interval,
+                                   ..Default::default() },
+               n_intervals: 0,
+               sum_intervals: 0.0,
+               sum_sq_intervals: 0.0 }
+    }
+
+    /// Adds a frame that was received by the render task at `receive_time` and
+    /// flipped at `flip_time`. Returns the number of missed vertical blanks.
+    pub(crate) fn add(&mut self, receive_time: Instant, flip_time: Instant) -> u32 {
+        let mut missed_vblanks = 0;
+
+        if let Some(last_flip) = self.last_flip {
+            let interval = flip_time.saturating_duration_since(last_flip).as_secs_f64();
+
+            // the vertical blank (counted from the last flip) the frame should have been
+            // flipped at, and the one it actually was flipped at
+            let waited = receive_time.saturating_duration_since(last_flip).as_secs_f64();
+            let target = (waited / self.refresh_interval).ceil().max(1.0);
+            let actual = (interval / self.refresh_interval).round();
+
+            missed_vblanks = (actual - target).max(0.0) as u32;
+
+            // only frames that were submitted back-to-back contribute to the interval
+            // statistics, as idle time would otherwise distort them
+            if target <= 1.0 {
+                self.n_intervals += 1;
+                self.sum_intervals += interval;
+                self.sum_sq_intervals += interval * interval;
+
+                let interval = Duration::from_secs_f64(interval);
+                if self.n_intervals == 1 || interval < self.stats.min_flip_interval {
+                    self.stats.min_flip_interval = interval;
+                }
+                if interval > self.stats.max_flip_interval {
+                    self.stats.max_flip_interval = interval;
+                }
+            }
+        }
+
+        self.last_flip = Some(flip_time);
+
+        self.stats.n_frames += 1;
+        if missed_vblanks > 0 {
+            self.stats.n_missed_frames += 1;
+            self.stats.n_missed_vblanks += missed_vblanks as u64;
+        }
+
+        missed_vblanks
+    }
+
+    /// Returns true if a frame flipped at `flip_time` is consistent with being
+    /// synchronised to the vertical blank, i.e. if it was flipped at least half
+    /// a refresh interval after the last frame. Must be called before the frame
+    /// is added.
+    pub(crate) fn is_synchronised(&self, flip_time: Instant) -> bool {
+        self.last_flip.map_or(true, |last_flip| {
+                          flip_time.saturating_duration_since(last_flip).as_secs_f64() >= 0.5 * self.refresh_interval
+                      })
+    }
+
+    /// Returns a summary of all frames added so far.
+    pub(crate) fn stats(&self) -> FrameStats {
+        let mut stats = self.stats;
+
+        if self.n_intervals > 0 {
+            let n = self.n_intervals as f64;
+            let mean = self.sum_intervals / n;
+            let var = (self.sum_sq_intervals / n - mean * mean).max(0.0);
+
+            stats.mean_flip_interval = Duration::from_secs_f64(mean);
+            stats.sd_flip_interval = Duration::from_secs_f64(var.sqrt());
+        }
+
+        stats
+    }
+
+    /// Resets the statistics. The last flip is kept, so that the next frame can
+    /// still be checked.
+    pub(crate) fn reset(&mut self) {
+        *self = Self { last_flip: self.last_flip,
+                       ..Self::new(Duration::from_secs_f64(self.refresh_interval)) };
+    }
+}
+
+/// Number of frames that are used to fit the regression model.
+const ESTIMATOR_WINDOW: usize = 120;
+
+/// Estimates flip times from noisy timestamps using a linear regression model.
+///
+/// Flips can only happen at vertical blanks, so the flip times lie on a grid
+/// `t = a + b * n`, where `n` is the number of refresh cycles since the first
+/// frame and `b` is the refresh interval. Each new timestamp is assigned to a
+/// refresh cycle based on the current estimate of the refresh interval, after
+/// which `a` and `b` are refitted on the most recent timestamps. The estimated
+/// flip time is the prediction of the model, which removes most of the jitter
+/// of the individual timestamps.
+#[derive(Debug, Clone)]
+pub(crate) struct FlipTimeEstimator {
+    /// Reference time all timestamps are measured relative to.
+    origin: Option<Instant>,
+    /// Recent samples as (refresh cycle, seconds since origin).
+    samples: VecDeque<(f64, f64)>,
+    /// Current estimate of the refresh interval in seconds.
+    refresh_interval: f64,
+}
+
+impl FlipTimeEstimator {
+    /// Create a new estimator with the given nominal refresh interval.
+    pub(crate) fn new(refresh_interval: Duration) -> Self {
+        Self { origin: None,
+               samples: VecDeque::with_capacity(ESTIMATOR_WINDOW),
+               refresh_interval: refresh_interval.as_secs_f64() }
+    }
+
+    /// Adds a new timestamp and returns the estimated flip time.
+    pub(crate) fn add(&mut self, timestamp: Instant) -> Instant {
+        let origin = *self.origin.get_or_insert(timestamp);
+        let t = timestamp.duration_since(origin).as_secs_f64();
+
+        // assign the timestamp to a refresh cycle
+        let n = match self.samples.back() {
+            Some(&(n_prev, t_prev)) => n_prev + ((t - t_prev) / self.refresh_interval).round().max(1.0),
+            None => 0.0,
+        };
+
+        if self.samples.len() == ESTIMATOR_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((n, t));
+
+        // we need at least three samples to fit a meaningful model
+        if self.samples.len() < 3 {
+            return timestamp;
+        }
+
+        let count = self.samples.len() as f64;
+        let mean_n = self.samples.iter().map(|(n, _)| n).sum::<f64>() / count;
+        let mean_t = self.samples.iter().map(|(_, t)| t).sum::<f64>() / count;
+
+        let cov = self.samples.iter().map(|(n, t)| (n - mean_n) * (t - mean_t)).sum::<f64>();
+        let var = self.samples.iter().map(|(n, _)| (n - mean_n).powi(2)).sum::<f64>();
+
+        let slope = cov / var;
+
+        // only accept plausible refresh intervals (the nominal interval might be off,
+        // but not by orders of magnitude)
+        if slope.is_finite() && slope > 0.0 && (slope / self.refresh_interval - 1.0).abs() < 0.5 {
+            self.refresh_interval = slope;
+        }
+
+        let intercept = mean_t - self.refresh_interval * mean_n;
+        let estimate = intercept + self.refresh_interval * n;
+
+        if estimate >= 0.0 {
+            origin + Duration::from_secs_f64(estimate)
+        } else {
+            timestamp
+        }
+    }
+}
+
+/// Time before a deadline at which `sleep_until()` stops sleeping and starts
+/// spinning, as the operating system might oversleep by a few milliseconds.
+const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
+/// Blocks until the given deadline. Sleeps for most of the time and spins for
+/// the last few milliseconds to wake up as close to the deadline as possible.
+pub(crate) fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline <= now {
+        return;
+    }
+
+    let remaining = deadline - now;
+    if remaining > SPIN_MARGIN {
+        std::thread::sleep(remaining - SPIN_MARGIN);
+    }
+
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFRESH_INTERVAL: f64 = 1.0 / 60.0;
+
+    /// Deterministic timing noise between 0 and 0.8 ms.
+    fn jitter(i: usize) -> f64 {
+        0.0004 * (1.0 + (i as f64 * 1.7).sin())
+    }
+
+    #[test]
+    fn flip_time_estimator_removes_jitter() {
+        let origin = Instant::now();
+        let mut estimator = FlipTimeEstimator::new(Duration::from_secs_f64(REFRESH_INTERVAL));
+
+        // the frame after frame 50 misses a vertical blank
+        let cycles = (0..100).map(|i| if i > 50 { i + 1 } else { i });
+
+        let mut max_error: f64 = 0.0;
+        for (i, cycle) in cycles.enumerate() {
+            let flip = cycle as f64 * REFRESH_INTERVAL;
+            let timestamp = origin + Duration::from_secs_f64(flip + jitter(i));
+            let estimate = estimator.add(timestamp).duration_since(origin).as_secs_f64();
+
+            // the model needs a few frames to settle; the mean jitter (0.4 ms) is a
+            // constant offset the model cannot (and should not) remove
+            if i >= 20 {
+                max_error = max_error.max((estimate - flip - 0.0004).abs());
+            }
+        }
+
+        assert!(max_error < 0.0002, "maximum error was {} s", max_error);
+        assert!((estimator.refresh_interval - REFRESH_INTERVAL).abs() < 1e-5);
+    }
+
+    #[test]
+    fn flip_time_estimator_passes_through_the_first_timestamps() {
+        let origin = Instant::now();
+        let mut estimator = FlipTimeEstimator::new(Duration::from_secs_f64(REFRESH_INTERVAL));
+
+        for i in 0..2 {
+            let timestamp = origin + Duration::from_secs_f64(i as f64 * REFRESH_INTERVAL + jitter(i));
+            assert_eq!(estimator.add(timestamp), timestamp);
+        }
+    }
+
+    #[test]
+    fn frame_drop_detector_counts_missed_vblanks() {
+        let origin = Instant::now();
+        let at = |cycles: f64| origin + Duration::from_secs_f64(cycles * REFRESH_INTERVAL);
+        let mut detector = FrameDropDetector::new(Duration::from_secs_f64(REFRESH_INTERVAL));
+
+        // the first frame has nothing to be compared against
+        assert_eq!(detector.add(at(0.0), at(0.5)), 0);
+
+        // frames received back-to-back are flipped on consecutive vertical blanks
+        assert_eq!(detector.add(at(0.6), at(1.5)), 0);
+        assert_eq!(detector.add(at(1.6), at(2.5)), 0);
+
+        // this frame was received in time, but only flipped two vertical blanks later
+        assert_eq!(detector.add(at(2.6), at(5.5)), 2);
+
+        let stats = detector.stats();
+        assert_eq!(stats.n_frames, 4);
+        assert_eq!(stats.n_missed_frames, 1);
+        assert_eq!(stats.n_missed_vblanks, 2);
+        assert!((stats.max_flip_interval.as_secs_f64() - 3.0 * REFRESH_INTERVAL).abs() < 1e-6);
+    }
+
+    #[test]
+    fn frame_drop_detector_detects_unsynchronised_flips() {
+        let origin = Instant::now();
+        let at = |cycles: f64| origin + Duration::from_secs_f64(cycles * REFRESH_INTERVAL);
+        let mut detector = FrameDropDetector::new(Duration::from_secs_f64(REFRESH_INTERVAL));
+
+        assert!(detector.is_synchronised(at(0.5)));
+        detector.add(at(0.0), at(0.5));
+        assert!(detector.is_synchronised(at(1.5)));
+        detector.add(at(0.6), at(1.5));
+
+        // a frame that was flipped only a fraction of a refresh interval later
+        // cannot have waited for the vertical blank
+        assert!(!detector.is_synchronised(at(1.7)));
+    }
+
+    #[test]
+    fn frame_drop_detector_ignores_idle_time() {
+        let origin = Instant::now();
+        let at = |cycles: f64| origin + Duration::from_secs_f64(cycles * REFRESH_INTERVAL);
+        let mut detector = FrameDropDetector::new(Duration::from_secs_f64(REFRESH_INTERVAL));
+
+        detector.add(at(0.0), at(0.5));
+        detector.add(at(0.6), at(1.5));
+
+        // after a long pause (e.g. waiting for a response), a frame flipped at the first
+        // vertical blank after it was received is on time
+        assert_eq!(detector.add(at(600.2), at(600.5)), 0);
+        // ... and one flipped a vertical blank later is not
+        assert_eq!(detector.add(at(1200.2), at(1201.5)), 1);
+
+        // the long intervals do not distort the interval statistics
+        let stats = detector.stats();
+        assert!((stats.max_flip_interval.as_secs_f64() - REFRESH_INTERVAL).abs() < 1e-6);
+        assert_eq!(stats.n_missed_frames, 1);
+    }
+
+    #[test]
+    fn refresh_rate_measurement_excludes_outliers() {
+        let mut intervals = (0..100).map(|i| REFRESH_INTERVAL + jitter(i) - 0.0004).collect::<Vec<_>>();
+        // two dropped frames and one interval that was cut short
+        intervals[10] = 2.0 * REFRESH_INTERVAL;
+        intervals[50] = 3.0 * REFRESH_INTERVAL;
+        intervals[70] = 0.5 * REFRESH_INTERVAL;
+
+        let measurement = RefreshRateMeasurement::from_intervals(&intervals, 60.0).unwrap();
+        assert_eq!(measurement.n_intervals, 100);
+        assert_eq!(measurement.n_outliers, 3);
+        assert_eq!(measurement.nominal_refresh_rate, 60.0);
+        assert!((measurement.refresh_rate - 60.0).abs() < 0.1, "refresh rate was {} Hz", measurement.refresh_rate);
+        assert!((measurement.mean_interval.as_secs_f64() - REFRESH_INTERVAL).abs() < 1e-4);
+        assert!(measurement.sd_interval.as_secs_f64() < 0.0004);
+    }
+
+    #[test]
+    fn refresh_rate_measurement_requires_valid_intervals() {
+        assert!(RefreshRateMeasurement::from_intervals(&[], 60.0).is_none());
+        assert!(RefreshRateMeasurement::from_intervals(&[0.0, 0.0, 0.0], 60.0).is_none());
+
+        let measurement = RefreshRateMeasurement::from_intervals(&[0.01], 100.0).unwrap();
+        assert!((measurement.refresh_rate - 100.0).abs() < 1e-9);
+        assert_eq!(measurement.n_outliers, 0);
+        assert_eq!(measurement.sd_interval, Duration::ZERO);
+    }
+}
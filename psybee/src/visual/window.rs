@@ -4,10 +4,10 @@ use std::collections::HashMap;
 use std::pin::Pin;
 #[cfg(target_arch = "wasm32")]
 use std::rc::Rc;
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use async_channel::{bounded, Receiver, Sender};
+use async_channel::{bounded, unbounded, Receiver, Sender};
 use async_lock::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use atomic_float::AtomicF64;
 use derive_debug::Dbg;
@@ -19,10 +19,18 @@ use palette::IntoColor;
 use wasm_bindgen::closure::Closure;
 
 use super::geometry::Size;
+use super::photodiode::{PhotodiodeMarker, PhotodiodeMarkerOptions};
 use super::stimuli::Stimulus;
-use crate::input::{Event, EventHandler, EventHandlerId, EventHandlingExt, EventKind, EventReceiver};
+use super::timing::{sleep_until, FlipTimeEstimator, FrameCallback, FrameCallbackId, FrameDropDetector, FrameStats, PresentationInfo, RefreshRateMeasurement};
+use crate::clock::{Clock, Timestamp};
+use crate::errors::PsybeeError;
+use crate::error;
+use crate::input::pointer::PointerState;
+use crate::input::{Event, EventHandler, EventHandlerId, EventHandlingExt, EventKind, EventReceiver, Modifiers, MouseButton};
 #[cfg(target_arch = "wasm32")]
 use crate::request_animation_frame;
+use crate::options::{BlockingStrategy, FrameDropCheckStrategy, TimestampingStrategy};
+use crate::triggers::{SentTrigger, TriggerOutput};
 use crate::visual::color::ColorFormat;
 use crate::{GPUState, RenderThreadChannelPayload};
 
@@ -30,12 +38,18 @@ use crate::{GPUState, RenderThreadChannelPayload};
 /// device, the wgpu queue, etc.
 #[derive(Debug)]
 pub struct InternalWindowState {
-    // the winit window
-    pub window: Arc<winit::window::Window>,
-    // the wgpu surface
-    pub surface: wgpu::Surface<'static>,
-    // the wgpu surface configuration
+    // the winit window (None for offscreen windows)
+    pub window: Option<Arc<winit::window::Window>>,
+    // the wgpu surface (None for offscreen windows)
+    pub surface: Option<wgpu::Surface<'static>>,
+    // the wgpu surface configuration (for offscreen windows, this describes the
+    // offscreen texture)
     pub config: wgpu::SurfaceConfiguration,
+    // the texture that frames are rendered into (only used for offscreen windows)
+    pub offscreen_texture: Option<wgpu::Texture>,
+    // the swapchain texture that was acquired after presenting the last frame
+    // (used to block until the end of the vertical blanking interval)
+    pub(crate) next_surface_texture: Mutex<Option<wgpu::SurfaceTexture>>,
 }
 
 /// How to block when presenting a frame.
@@ -53,6 +67,10 @@ pub struct Window {
     /// The current mouse position. None if the mouse is not over the
     /// window.
     pub(crate) mouse_position: Arc<Mutex<Option<(Size, Size)>>>,
+    /// The current state of the modifier keys.
+    pub(crate) modifiers: Arc<Mutex<Modifiers>>,
+    /// The pressed mouse buttons and velocity of the mouse cursor.
+    pub(crate) pointer: Arc<Mutex<PointerState>>,
     /// Stores if the mouse cursor is currently visible.
     pub(crate) mouse_cursor_visible: Arc<AtomicBool>,
 
@@ -71,12 +89,29 @@ pub struct Window {
     pub(crate) frame_channel_receiver: Receiver<Arc<Mutex<Frame>>>,
     /// Channel for frame consumption. Used by the render task to notify the
     /// experiment task that a frame has been consumed.
-    pub(crate) frame_consumed_channel_sender: Sender<bool>,
+    pub(crate) frame_consumed_channel_sender: Sender<PresentationInfo>,
     /// Channel for frame consumption. Used by the render task to notify the
     /// experiment task that a frame has been consumed.
-    pub(crate) frame_consumed_channel_receiver: Receiver<bool>,
+    pub(crate) frame_consumed_channel_receiver: Receiver<PresentationInfo>,
+    /// Number of frames that have been submitted but not yet consumed by the
+    /// render task.
+    pub(crate) frames_in_flight: Arc<AtomicU32>,
+    /// Number of frames that have been submitted to the render task.
+    pub(crate) frames_submitted: Arc<AtomicU64>,
+    /// Presentation info of the last frame that has been consumed by the
+    /// render task.
+    pub(crate) last_presentation_info: Arc<Mutex<Option<PresentationInfo>>>,
+    /// Keeps track of dropped frames.
+    pub(crate) frame_drop_detector: Arc<Mutex<Option<FrameDropDetector>>>,
+    /// Callbacks that are called for every presented frame.
+    #[dbg(placeholder = "...")]
+    pub(crate) frame_callbacks: Arc<RwLock<HashMap<FrameCallbackId, FrameCallback>>>,
     /// render_task_sender
     pub(crate) render_task_sender: Sender<RenderThreadChannelPayload>,
+    /// Senders for pending frame captures. The next frame that is presented will
+    /// be copied back to the CPU and sent to all of them.
+    #[dbg(placeholder = "...")]
+    pub(crate) pending_captures: Arc<Mutex<Vec<Sender<Result<image::RgbaImage, PsybeeError>>>>>,
 
     // PHYSICAL WINDOW PROPERTIES
     /// Physical width of the window in millimeters.
@@ -89,10 +124,26 @@ pub struct Window {
     pub width_px: Arc<AtomicU32>,
     /// The window's height in pixels.
     pub height_px: Arc<AtomicU32>,
+    /// The refresh rate (in Hz) reported by the operating system.
+    pub(crate) nominal_refresh_rate: Arc<AtomicF64>,
+    /// The refresh rate (in Hz) measured with `measure_refresh_rate()`.
+    pub(crate) measured_refresh_rate: Arc<Mutex<Option<f64>>>,
 
     /// Vector of stimuli that will be added to each frame automatically.
     #[dbg(placeholder = "...")]
     pub stimuli: Arc<Mutex<Vec<Box<dyn Stimulus>>>>,
+    /// The photodiode marker that will be added to each frame automatically
+    /// (if enabled).
+    #[dbg(placeholder = "...")]
+    pub(crate) photodiode_marker: Arc<Mutex<Option<PhotodiodeMarker>>>,
+    /// The output that trigger codes added to frames (see
+    /// `Frame::add_trigger()`) are sent to.
+    #[dbg(placeholder = "...")]
+    pub(crate) trigger_output: Arc<Mutex<Option<Box<dyn TriggerOutput>>>>,
+    /// The trigger codes that have been sent so far.
+    pub(crate) sent_triggers: Arc<Mutex<Vec<SentTrigger>>>,
+    /// The experiment clock of the session the window belongs to.
+    pub(crate) clock: Clock,
 
     // EVENT HANDLING
     /// Event handlers for the window. Handlers are stored in a HashMap with
@@ -105,6 +156,91 @@ pub struct Window {
 }
 
 impl Window {
+    /// Creates a new window handle from the given window state. The size of the
+    /// window is taken from the surface configuration.
+    pub(crate) fn new(window_state: InternalWindowState,
+                      gpu_state: Arc<RwLock<GPUState>>,
+                      render_task_sender: Sender<RenderThreadChannelPayload>,
+                      clock: Clock)
+                      -> Self {
+        // create channel for frame submission (the number of frames in flight is
+        // limited in `present()`, depending on the blocking strategy)
+        let (frame_sender, frame_receiver): (Sender<Arc<Mutex<Frame>>>, Receiver<Arc<Mutex<Frame>>>) = unbounded();
+
+        let (frame_ok_sender, frame_ok_receiver): (Sender<PresentationInfo>, Receiver<PresentationInfo>) = unbounded();
+
+        // create channel for physical input
+        let (mut event_broadcast_sender, physical_input_receiver) = async_broadcast::broadcast(10_000);
+        event_broadcast_sender.set_overflow(true);
+        // deactivate the receiver
+        let event_broadcast_receiver = physical_input_receiver.deactivate();
+
+        let width_px = window_state.config.width;
+        let height_px = window_state.config.height;
+
+        // offscreen windows (and monitors that do not report their refresh rate) are
+        // assumed to run at 60 Hz
+        let nominal_refresh_rate = window_state.window
+                                               .as_ref()
+                                               .and_then(|window| window.current_monitor())
+                                               .and_then(|monitor| monitor.refresh_rate_millihertz())
+                                               .map(|mhz| mhz as f64 / 1000.0)
+                                               .unwrap_or(60.0);
+
+        // create handle
+        let window = Window { state: Arc::new(RwLock::new(window_state)),
+                              gpu_state,
+                              mouse_position: Arc::new(Mutex::new(None)),
+                              modifiers: Arc::new(Mutex::new(Modifiers::empty())),
+                              pointer: Arc::new(Mutex::new(PointerState::default())),
+                              mouse_cursor_visible: Arc::new(AtomicBool::new(true)),
+                              event_broadcast_receiver,
+                              event_broadcast_sender,
+                              frame_channel_sender: frame_sender,
+                              frame_channel_receiver: frame_receiver,
+                              frame_consumed_channel_sender: frame_ok_sender,
+                              frame_consumed_channel_receiver: frame_ok_receiver,
+                              frames_in_flight: Arc::new(AtomicU32::new(0)),
+                              frames_submitted: Arc::new(AtomicU64::new(0)),
+                              last_presentation_info: Arc::new(Mutex::new(None)),
+                              frame_drop_detector: Arc::new(Mutex::new(None)),
+                              frame_callbacks: Arc::new(RwLock::new(HashMap::new())),
+                              physical_width: Arc::new(AtomicF64::new(300.0)),
+                              viewing_distance: Arc::new(AtomicF64::new(57.0)),
+                              color_format: ColorFormat::SRGBA8,
+                              width_px: Arc::new(AtomicU32::new(width_px)),
+                              height_px: Arc::new(AtomicU32::new(height_px)),
+                              nominal_refresh_rate: Arc::new(AtomicF64::new(nominal_refresh_rate)),
+                              measured_refresh_rate: Arc::new(Mutex::new(None)),
+                              render_task_sender,
+                              pending_captures: Arc::new(Mutex::new(vec![])),
+                              stimuli: Arc::new(Mutex::new(vec![])),
+                              photodiode_marker: Arc::new(Mutex::new(None)),
+                              trigger_output: Arc::new(Mutex::new(None)),
+                              sent_triggers: Arc::new(Mutex::new(Vec::new())),
+                              clock,
+                              event_handlers: Arc::new(RwLock::new(HashMap::new())),
+                              options: Arc::new(Mutex::new(crate::options::GlobalOptions::default())) };
+
+        let win_clone = window.clone();
+        // add a default event handler for mouse move events, which updates the mouse
+        // position
+        window.add_event_handler(EventKind::CursorMoved, move |event| {
+                  if let Some(pos) = event.position() {
+                      win_clone.mouse_position.lock_blocking().replace(pos.clone());
+                  };
+                  false
+              });
+
+        window
+    }
+
+    /// Returns true if this window renders into an offscreen texture instead of
+    /// a window on the screen.
+    pub fn is_offscreen(&self) -> bool {
+        self.state.read_blocking().surface.is_none()
+    }
+
     /// Returns a MutexGuard to the WindowState behind the mutex.
     pub fn read_window_state_blocking(&self) -> RwLockReadGuard<InternalWindowState> {
         return self.state.read_blocking();
@@ -165,22 +301,312 @@ impl Window {
 
     /// Submits a frame to the render task. This will in turn call the prepare()
     /// and render() functions of all renderables in the frame.
-    /// This will block until the frame has been consumed by the render task.
-    pub fn present(&self, frame: Frame) {
+    ///
+    /// How long this blocks depends on the blocking strategy set in the
+    /// window's options. With `BlockingStrategy::DoNotBlock`, this returns
+    /// immediately unless `max_frames_in_flight` frames are already waiting to
+    /// be rendered. All other strategies block until the render task is done
+    /// with the frame.
+    ///
+    /// Returns the presentation info of the frame. When not blocking, the frame
+    /// has not been presented yet when this returns, so the returned info only
+    /// contains the submit time. Use `last_presentation_info()` to retrieve
+    /// the full information later.
+    pub fn present(&self, frame: Frame) -> PresentationInfo {
+        let options = *self.options.lock_blocking();
+
         let frame_sender = self.frame_channel_sender.clone();
         let frame_ok_receiver = self.frame_consumed_channel_receiver.clone();
 
         // submit frame to channel
+        let submit_time = web_time::Instant::now();
+        let mut frame = frame;
+        frame.submit_time = Some(submit_time);
         block_on(frame_sender.send(Arc::new(Mutex::new(frame)))).expect("Failed to send frame");
+        let frame_index = self.frames_submitted.fetch_add(1, Ordering::SeqCst);
+        self.frames_in_flight.fetch_add(1, Ordering::SeqCst);
+
+        let max_frames_in_flight = match options.blocking_strategy {
+            BlockingStrategy::DoNotBlock => options.max_frames_in_flight.max(1),
+            _ => 0,
+        };
+
+        let mut presentation_info = PresentationInfo { frame_index,
+                                                       submit_time,
+                                                       flip_time: None,
+                                                       missed_frame: false,
+                                                       missed_vblanks: 0 };
+
+        // collect frames that have already been consumed without blocking, then wait
+        // for frames to be consumed until we are below the limit
+        loop {
+            let info = if let Ok(info) = frame_ok_receiver.try_recv() {
+                info
+            } else if self.frames_in_flight.load(Ordering::SeqCst) > max_frames_in_flight {
+                block_on(frame_ok_receiver.recv()).expect("Failed to receive frame_ok")
+            } else {
+                break;
+            };
 
-        // wait for frame to be consumed
-        block_on(frame_ok_receiver.recv()).expect("Failed to receive frame_ok");
+            self.frames_in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            if info.frame_index == frame_index {
+                presentation_info = info;
+            }
+        }
+
+        presentation_info
+    }
+
+    /// Presents the frame and keeps it on the screen for `n_frames` refresh
+    /// cycles. The frame is re-submitted until it has been visible for the
+    /// requested number of refreshes, so dropped frames do not extend the
+    /// duration. This returns after the last refresh cycle has started, so the
+    /// next frame should be presented immediately afterwards.
+    ///
+    /// Returns the presentation info of the first presentation, i.e. the onset
+    /// of the frame.
+    ///
+    /// Precise durations require a blocking strategy that blocks until the end
+    /// of the vertical blanking interval. Otherwise, the frame is simply
+    /// presented `n_frames` times.
+    ///
+    /// Panics if `n_frames` is 0.
+    pub fn present_for(&self, frame: Frame, n_frames: u32) -> PresentationInfo {
+        assert!(n_frames > 0, "a frame must be presented for at least one refresh cycle");
+
+        let refresh_interval = 1.0 / self.refresh_rate();
+
+        // triggers are only sent at the onset of the frame
+        let mut frame = frame;
+        let onset = self.present(frame.clone());
+        frame.triggers.clear();
+
+        let mut n_presented = 1;
+        loop {
+            // number of refresh cycles the frame has been visible for (including the
+            // current one)
+            let n_visible = match (onset.flip_time, self.last_presentation_info().and_then(|info| info.flip_time)) {
+                (Some(onset_time), Some(last_flip)) => {
+                    (last_flip.duration_since(onset_time).as_secs_f64() / refresh_interval).round() as u32 + 1
+                }
+                _ => n_presented,
+            };
+
+            if n_visible >= n_frames {
+                break;
+            }
+
+            self.present(frame.clone());
+            n_presented += 1;
+        }
+
+        onset
+    }
+
+    /// Presents the frame at the vertical blank closest to the given deadline.
+    /// The time of the next vertical blanks is predicted from the last flip and
+    /// the refresh rate, and the frame is submitted just after the vertical
+    /// blank preceding the target one. Between now and then, the previous frame
+    /// stays on the screen.
+    ///
+    /// If the deadline is too close (or has already passed), the frame is
+    /// presented as soon as possible. Check the flip time of the returned
+    /// presentation info to see when the frame was actually presented.
+    ///
+    /// Precise timing requires a blocking strategy that blocks until the end of
+    /// the vertical blanking interval.
+    pub fn present_at(&self, frame: Frame, deadline: web_time::Instant) -> PresentationInfo {
+        let refresh_interval = std::time::Duration::from_secs_f64(1.0 / self.refresh_rate());
+
+        let last_flip = self.last_presentation_info().and_then(|info| info.flip_time);
+
+        let submit_time = match last_flip {
+            Some(last_flip) if deadline > last_flip => {
+                // find the vertical blank closest to the deadline and submit shortly after the
+                // vertical blank before it
+                let n_cycles = (deadline.duration_since(last_flip).as_secs_f64() / refresh_interval.as_secs_f64()).round().max(1.0);
+                let target = last_flip + refresh_interval.mul_f64(n_cycles);
+                target.checked_sub(refresh_interval - refresh_interval / 10)
+            }
+            // without a reference flip, the frame will be flipped within one refresh cycle
+            // after submitting it
+            _ => deadline.checked_sub(refresh_interval / 2),
+        };
+
+        if let Some(submit_time) = submit_time {
+            sleep_until(submit_time);
+        }
+
+        self.present(frame)
+    }
+
+    /// Returns the presentation info of the last frame that has been presented
+    /// on this window, or None if no frame has been presented yet.
+    pub fn last_presentation_info(&self) -> Option<PresentationInfo> {
+        *self.last_presentation_info.lock_blocking()
+    }
+
+    /// Returns a summary of the frames that have been presented on this window
+    /// so far, including the number of dropped frames. Dropped frames can only be
+    /// detected when blocking until the end of the vertical blanking interval
+    /// (see `BlockingStrategy`), so no frames are counted otherwise.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_drop_detector
+            .lock_blocking()
+            .as_ref()
+            .map(|detector| detector.stats())
+            .unwrap_or_default()
+    }
+
+    /// Resets the frame statistics returned by `frame_stats()`.
+    pub fn reset_frame_stats(&self) {
+        if let Some(detector) = self.frame_drop_detector.lock_blocking().as_mut() {
+            detector.reset();
+        }
+    }
+
+    /// Adds a callback that is called on the render thread for every frame that
+    /// is presented on this window. Returns an id that can be used to remove the
+    /// callback again.
+    pub fn add_frame_callback<F>(&self, callback: F) -> FrameCallbackId
+        where F: Fn(&PresentationInfo) + 'static + Send + Sync
+    {
+        // find a free id
+        let id = loop {
+            let id = rand::random::<FrameCallbackId>();
+            if !self.frame_callbacks.read_blocking().contains_key(&id) {
+                break id;
+            }
+        };
+
+        self.frame_callbacks.write_blocking().insert(id, Box::new(callback));
+
+        return id;
+    }
+
+    /// Removes a callback that was added with `add_frame_callback()`.
+    pub fn remove_frame_callback(&self, id: FrameCallbackId) {
+        self.frame_callbacks.write_blocking().remove(&id);
+    }
+
+    /// Measures the refresh rate of the window by presenting `n_frames` blank
+    /// frames and measuring the intervals between flips. The measured refresh
+    /// rate is stored on the window and used from then on, e.g. to convert
+    /// between seconds and frames and to detect dropped frames.
+    ///
+    /// Make sure nothing else is presented on the window while measuring.
+    pub fn measure_refresh_rate(&self, n_frames: u32) -> Result<RefreshRateMeasurement, PsybeeError> {
+        // number of frames presented before measuring, as the first few frames are
+        // often irregular
+        const WARMUP_FRAMES: u32 = 10;
+
+        if self.is_offscreen() {
+            error!("The refresh rate cannot be measured for offscreen windows.");
+        }
+
+        if n_frames < 2 {
+            error!("At least two frames are needed to measure the refresh rate.");
+        }
+
+        // we need to block until each frame has been flipped and want the raw timestamps
+        let original_options = *self.options.lock_blocking();
+        {
+            let mut options = self.options.lock_blocking();
+            options.blocking_strategy = BlockingStrategy::BlockUntilVBlankEnd;
+            options.timestamping_strategy = TimestampingStrategy::BlockingSubmit;
+        }
+
+        for _ in 0..WARMUP_FRAMES {
+            self.present(self.empty_frame());
+        }
+
+        let flip_times = (0..n_frames).filter_map(|_| self.present(self.empty_frame()).flip_time)
+                                      .collect::<Vec<_>>();
+
+        {
+            let mut options = self.options.lock_blocking();
+            options.blocking_strategy = original_options.blocking_strategy;
+            options.timestamping_strategy = original_options.timestamping_strategy;
+        }
+
+        let intervals = flip_times.windows(2)
+                                  .map(|w| w[1].duration_since(w[0]).as_secs_f64())
+                                  .collect::<Vec<_>>();
+
+        let nominal_refresh_rate = self.nominal_refresh_rate();
+
+        let Some(measurement) = RefreshRateMeasurement::from_intervals(&intervals, nominal_refresh_rate) else {
+            error!("Failed to measure the refresh rate: no valid flip intervals were recorded.");
+        };
+
+        log::info!("Measured refresh rate: {:.3} Hz (SD of flip intervals: {:?}, {} outliers)",
+                   measurement.refresh_rate,
+                   measurement.sd_interval,
+                   measurement.n_outliers);
+
+        if (measurement.refresh_rate / nominal_refresh_rate - 1.0).abs() > 0.01 {
+            log::warn!("The measured refresh rate ({:.3} Hz) differs from the refresh rate reported by the operating system ({:.3} Hz).",
+                       measurement.refresh_rate,
+                       nominal_refresh_rate);
+        }
+
+        self.measured_refresh_rate.lock_blocking().replace(measurement.refresh_rate);
+        self.frame_drop_detector
+            .lock_blocking()
+            .replace(FrameDropDetector::new(measurement.mean_interval));
+
+        Ok(measurement)
+    }
+
+    /// Returns the refresh rate of the window in Hz. This is the refresh rate
+    /// measured with `measure_refresh_rate()` if available, and the nominal
+    /// refresh rate reported by the operating system otherwise.
+    pub fn refresh_rate(&self) -> f64 {
+        if let Some(refresh_rate) = *self.measured_refresh_rate.lock_blocking() {
+            return refresh_rate;
+        }
+        self.nominal_refresh_rate()
+    }
+
+    /// Converts a duration in seconds to a number of frames, rounded to the
+    /// nearest frame.
+    pub fn secs_to_frames(&self, secs: f64) -> u64 {
+        (secs * self.refresh_rate()).round().max(0.0) as u64
+    }
+
+    /// Converts a number of frames to a duration in seconds.
+    pub fn frames_to_secs(&self, n_frames: u64) -> f64 {
+        n_frames as f64 / self.refresh_rate()
+    }
+
+    /// Returns the nominal refresh rate (in Hz) of the monitor the window was
+    /// created on, as reported by the operating system. Falls back to 60 Hz
+    /// for offscreen windows or if the refresh rate cannot be determined.
+    pub fn nominal_refresh_rate(&self) -> f64 {
+        self.nominal_refresh_rate.load(Ordering::Relaxed)
     }
 
     pub fn close(&self) {
         todo!()
     }
 
+    /// Requests a capture of the next frame that is presented on this window.
+    /// The frame is copied back from the GPU exactly as it was presented, so
+    /// this can be used to archive what a participant saw. Use
+    /// `FrameCapture::wait()` after presenting the frame to retrieve the image.
+    pub fn capture_next_frame(&self) -> FrameCapture {
+        let (sender, receiver) = bounded(1);
+
+        if !self.state.read_blocking().config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+            let _ = sender.try_send(Err(PsybeeError::FrameCaptureError("the surface of this window does not support copying frames back to the CPU".to_string())));
+        } else {
+            self.pending_captures.lock_blocking().push(sender);
+        }
+
+        FrameCapture { receiver }
+    }
+
     /// Returns the color format.
     #[deprecated(note = "Color format handling will change in the future.")]
     pub fn get_color_format(&self) -> ColorFormat {
@@ -189,7 +615,9 @@ impl Window {
 
     /// Set the visibility of the mouse cursor.
     pub fn set_cursor_visible(&self, visible: bool) {
-        self.state.read_blocking().window.set_cursor_visible(visible);
+        if let Some(window) = &self.state.read_blocking().window {
+            window.set_cursor_visible(visible);
+        }
         self.mouse_cursor_visible.store(visible, Ordering::Relaxed);
     }
 
@@ -203,6 +631,22 @@ impl Window {
         self.mouse_position.lock_blocking().clone()
     }
 
+    /// Returns the modifier keys that are currently held down.
+    pub fn modifiers(&self) -> Modifiers {
+        *self.modifiers.lock_blocking()
+    }
+
+    /// Returns the mouse buttons that are currently held down.
+    pub fn pressed_mouse_buttons(&self) -> Vec<MouseButton> {
+        self.pointer.lock_blocking().buttons()
+    }
+
+    /// Returns the velocity of the mouse cursor in pixels per second (with y
+    /// pointing up), measured between the last two cursor movements.
+    pub fn pointer_velocity(&self) -> (f64, f64) {
+        self.pointer.lock_blocking().velocity()
+    }
+
     /// Returns the 4x4 matrix than when applied to pixel coordinates will transform
     /// them to normalized device coordinates. Pixel coordinates are in a
     /// coordinate system with (0.0,0.0) in the center of the screen and
@@ -222,9 +666,9 @@ impl Window {
 
     // Create a new frame with a black background.
     pub fn get_frame(&self) -> Frame {
-        let mut frame = Frame { stimuli: Arc::new(Mutex::new(Vec::new())),
-                                color_format: self.color_format,
-                                bg_color: super::color::RawRgba { r: 0.0, g: 0.0, b: 0.0, a: 1.0 } };
+        let mut frame = self.empty_frame();
+
+        frame.photodiode_marker = self.photodiode_marker.lock_blocking().clone();
 
         // TODO: is this efficient?
         for stimulus in self.stimuli.lock_blocking().iter() {
@@ -234,6 +678,55 @@ impl Window {
         return frame;
     }
 
+    // Create a new frame with a black background, without the window's stimuli.
+    fn empty_frame(&self) -> Frame {
+        Frame { stimuli: Arc::new(Mutex::new(Vec::new())),
+                color_format: self.color_format,
+                bg_color: super::color::RawRgba { r: 0.0, g: 0.0, b: 0.0, a: 1.0 },
+                window: self.clone(),
+                photodiode_marker: None,
+                marked: false,
+                triggers: Vec::new(),
+                submit_time: None }
+    }
+
+    /// Enables the photodiode marker with the given options, or disables it if
+    /// `None` is passed. When enabled, the marker is drawn on top of all
+    /// other stimuli on every frame obtained with `get_frame()`, and changes
+    /// its colour on frames marked with `Frame::mark()`.
+    pub fn set_photodiode_marker(&self, options: Option<PhotodiodeMarkerOptions>) {
+        let marker = options.map(|options| PhotodiodeMarker::new(self, options));
+        *self.photodiode_marker.lock_blocking() = marker;
+    }
+
+    /// Returns the options of the photodiode marker, or None if it is disabled.
+    pub fn photodiode_marker(&self) -> Option<PhotodiodeMarkerOptions> {
+        self.photodiode_marker
+            .lock_blocking()
+            .as_ref()
+            .map(|marker| marker.options.clone())
+    }
+
+    /// Sets the output that trigger codes added to frames (see
+    /// `Frame::add_trigger()`) are sent to, or removes it if `None` is passed.
+    /// Without an output, trigger codes are only logged.
+    pub fn set_trigger_output(&self, output: Option<Box<dyn TriggerOutput>>) {
+        *self.trigger_output.lock_blocking() = output;
+    }
+
+    /// Returns the trigger codes that have been sent for the frames presented
+    /// on this window so far, together with the flip times of the frames and
+    /// the times the codes were written.
+    pub fn sent_triggers(&self) -> Vec<SentTrigger> {
+        self.sent_triggers.lock_blocking().clone()
+    }
+
+    /// Returns the experiment clock of the session the window belongs to (see
+    /// `ExperimentManager::clock()`).
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
     /// Returns the physical width of the window in millimeters.
     pub fn physical_width(&self) -> f64 {
         self.physical_width.load(Ordering::Relaxed)
@@ -393,59 +886,118 @@ pub async fn render_task(window: Window) {
     {
         let flip_count = Arc::new(AtomicU32::new(0));
 
+        let mut frame_index: u64 = 0;
+        let mut refresh_interval = 1.0 / window.refresh_rate();
+        let mut flip_time_estimator = FlipTimeEstimator::new(std::time::Duration::from_secs_f64(refresh_interval));
+        let mut warned_graphics_api_timestamps = false;
+        let mut warned_graphics_api_frame_drops = false;
+        let mut warned_unsynchronised_flips = false;
+        let trigger_worker = TriggerWorker::new(&window);
+
+        window.frame_drop_detector
+              .lock_blocking()
+              .replace(FrameDropDetector::new(std::time::Duration::from_secs_f64(refresh_interval)));
+
         loop {
             // wait for frame to be submitted
             let frame = rx.recv().await.unwrap();
+            let receive_time = web_time::Instant::now();
 
             // acquire lock on frame
             let mut frame = frame.lock_blocking();
 
+            // the options might have changed since the last frame
+            let options = *window.options.lock_blocking();
+
+            // the refresh rate might have been measured since the last frame
+            if let Some(refresh_rate) = *window.measured_refresh_rate.lock_blocking() {
+                if (1.0 / refresh_rate - refresh_interval).abs() > f64::EPSILON {
+                    refresh_interval = 1.0 / refresh_rate;
+                    flip_time_estimator = FlipTimeEstimator::new(std::time::Duration::from_secs_f64(refresh_interval));
+                }
+            }
+
             // acquire lock on window
             let window_state = window.read_window_state_blocking();
             let gpu_state = window.read_gpu_state_blocking();
 
-            let suface_texture = window_state.surface.get_current_texture().expect("Failed to acquire next swap chain texture");
+            // on-screen windows render into the next swapchain texture, offscreen windows
+            // into their offscreen texture. If we already acquired the next swapchain
+            // texture after presenting the last frame, we use that one.
+            let suface_texture = window_state.surface.as_ref().map(|surface| {
+                                                                  window_state.next_surface_texture
+                                                                              .lock_blocking()
+                                                                              .take()
+                                                                              .unwrap_or_else(|| {
+                                                                                  surface.get_current_texture()
+                                                                                         .expect("Failed to acquire next swap chain texture")
+                                                                              })
+                                                              });
+
+            let target_texture = match &suface_texture {
+                Some(suface_texture) => &suface_texture.texture,
+                None => window_state.offscreen_texture
+                                    .as_ref()
+                                    .expect("Window has neither a surface nor an offscreen texture. This is likely a bug, please report it."),
+            };
 
-            let view = suface_texture.texture
-                                     .create_view(&wgpu::TextureViewDescriptor { format: Some(wgpu::TextureFormat::Bgra8Unorm),
+            let view = target_texture.create_view(&wgpu::TextureViewDescriptor { format: Some(wgpu::TextureFormat::Bgra8Unorm),
                                                                                  ..wgpu::TextureViewDescriptor::default() });
 
             let mut encoder = gpu_state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-            // start timer
-
-            // clear the frame
-            {
-                // clear the frame (once the lifetime annoyance is fixed, this can be removed
-                // only a single render pass is needed using the LoadOp::Clear
-                // option)
-                let _rpass =
-                    &mut encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: None,
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(frame.bg_color.into()),
-                                store: wgpu::StoreOp::Store,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                        timestamp_writes: None,
-                        occlusion_query_set: None,
-                    });
-            }
-
             let t_start = std::time::Instant::now();
-            frame.prepare(&window, &window_state, &gpu_state).await;
-
-            frame.render(&mut encoder, &view);
+            frame.prepare_and_render(&window, &window_state, &gpu_state, &mut encoder, &view).await;
             log::warn!("Frame - Time to prepare and render: {:?}", t_start.elapsed());
 
+            // if a capture was requested, copy the frame to a buffer before presenting it
+            let pending_captures = std::mem::take(&mut *window.pending_captures.lock_blocking());
+            let capture_buffer = if pending_captures.is_empty() {
+                None
+            } else {
+                Some(copy_texture_to_buffer(&gpu_state.device, &mut encoder, target_texture))
+            };
+
             let _ = gpu_state.queue.submit(Some(encoder.finish()));
 
-            // present the frame
-            suface_texture.present();
+            if let Some(capture_buffer) = capture_buffer {
+                let size = target_texture.size();
+                let result = read_buffer_to_image(&gpu_state.device, &capture_buffer, size.width, size.height, target_texture.format());
+
+                for sender in pending_captures {
+                    let result = match &result {
+                        Ok(image) => Ok(image.clone()),
+                        Err(e) => Err(PsybeeError::FrameCaptureError(e.to_string())),
+                    };
+                    let _ = sender.try_send(result);
+                }
+            }
+
+            // present the frame (offscreen frames stay in the offscreen texture)
+            if let Some(suface_texture) = suface_texture {
+                suface_texture.present();
+            }
+
+            // block according to the blocking strategy before notifying the sender
+            match options.blocking_strategy {
+                BlockingStrategy::DoNotBlock => {}
+                BlockingStrategy::BlockUntilVBlankStart => {
+                    // wait until the GPU has finished rendering the frame, at which point it is
+                    // queued for presentation at the next vertical blank
+                    gpu_state.device.poll(wgpu::Maintain::Wait);
+                }
+                BlockingStrategy::BlockUntilVBlankEnd | BlockingStrategy::BlockUntilVBlankEndVerified => {
+                    gpu_state.device.poll(wgpu::Maintain::Wait);
+
+                    // acquiring the next swapchain texture blocks until the frame we just
+                    // presented has been flipped to the screen (with a maximum frame latency
+                    // of 1). The texture is kept and used for the next frame.
+                    if let Some(surface) = &window_state.surface {
+                        let next_texture = surface.get_current_texture().expect("Failed to acquire next swap chain texture");
+                        window_state.next_surface_texture.lock_blocking().replace(next_texture);
+                    }
+                }
+            }
 
             // #[cfg(target_os = "windows")]
             // {
@@ -472,12 +1024,214 @@ pub async fn render_task(window: Window) {
             //     unsafe { &window_state.surface.as_hal::<wgpu::core::api::Dx12, _, _>(hal_surface_callback) }.unwrap();
             // }
 
+            // timestamp the frame
+            let timestamp = web_time::Instant::now();
+
+            if matches!(options.timestamping_strategy,
+                        TimestampingStrategy::GraphicsAPI | TimestampingStrategy::GraphicsAPIEstimate)
+               && !warned_graphics_api_timestamps
+            {
+                log::warn!("Timestamps from the graphics API are not supported yet, falling back to timestamps from the blocking submit call.");
+                warned_graphics_api_timestamps = true;
+            }
+
+            let flip_time = match options.timestamping_strategy {
+                TimestampingStrategy::BlockingSubmit | TimestampingStrategy::GraphicsAPI => timestamp,
+                TimestampingStrategy::BlockingSubmitEstimate | TimestampingStrategy::GraphicsAPIEstimate => flip_time_estimator.add(timestamp),
+            };
+
+            // send the frame's triggers as close to the flip as possible
+            if !frame.triggers.is_empty() {
+                trigger_worker.send(&window, &frame.triggers, frame_index, flip_time);
+            }
+
+            if matches!(options.frame_drop_check_strategy, FrameDropCheckStrategy::GraphicsAPI) && !warned_graphics_api_frame_drops {
+                log::warn!("Frame statistics from the graphics API are not supported yet, falling back to timing-based frame drop detection.");
+                warned_graphics_api_frame_drops = true;
+            }
+
+            // dropped frames can only be detected if we know when the frame was flipped
+            let blocks_until_flip = matches!(options.blocking_strategy,
+                                             BlockingStrategy::BlockUntilVBlankEnd | BlockingStrategy::BlockUntilVBlankEndVerified);
+            let missed_vblanks = if blocks_until_flip {
+                let mut detector = window.frame_drop_detector.lock_blocking();
+
+                // verify that the frame was actually flipped at a vertical blank, which is
+                // not the case if the swapchain does not wait for it (e.g. without vsync).
+                // Offscreen frames are never flipped, so there is nothing to verify.
+                if matches!(options.blocking_strategy, BlockingStrategy::BlockUntilVBlankEndVerified)
+                   && window_state.surface.is_some()
+                   && !warned_unsynchronised_flips
+                   && !detector.as_ref().map_or(true, |detector| detector.is_synchronised(flip_time))
+                {
+                    log::warn!("Frame {} was flipped less than half a refresh interval after the previous frame. Presentation does not seem to be synchronised to the vertical blank, so flip times and dropped frames cannot be verified.",
+                               frame_index);
+                    warned_unsynchronised_flips = true;
+                }
+
+                detector.as_mut().map_or(0, |detector| detector.add(receive_time, flip_time))
+            } else {
+                0
+            };
+
+            if missed_vblanks > 0 {
+                log::warn!("Frame {} missed {} vertical blank(s)", frame_index, missed_vblanks);
+            }
+
+            let info = PresentationInfo { frame_index,
+                                          submit_time: frame.submit_time.unwrap_or(receive_time),
+                                          flip_time: Some(flip_time),
+                                          missed_frame: missed_vblanks > 0,
+                                          missed_vblanks };
+            frame_index += 1;
+
+            window.last_presentation_info.lock_blocking().replace(info);
+
             // notify sender that frame has been consumed
-            let _ = block_on(tx.send(true));
+            let _ = block_on(tx.send(info));
+
+            // call the per-frame callbacks
+            for callback in window.frame_callbacks.read_blocking().values() {
+                callback(&info);
+            }
+        }
+    }
+}
+
+/// Trigger codes of a presented frame that are (still) to be sent by the
+/// trigger worker.
+#[cfg(not(target_arch = "wasm32"))]
+struct TriggerJob {
+    codes: Vec<u8>,
+    frame_index: u64,
+    flip_time: Timestamp,
+    /// The time the render task wrote the code preceding `codes`, which has to
+    /// be reset after the pulse width.
+    reset_after: Option<Timestamp>,
+    /// True if the codes were queued because the worker was still busy with
+    /// an earlier frame.
+    delayed: bool,
+}
+
+/// Sends the trigger codes of presented frames to the window's trigger output.
+/// If the output resets automatically, only the first code of a frame is
+/// written on the render task; the resets (and any further codes) are written
+/// by a worker thread, so the render task does not block for the pulse width.
+/// Codes of a frame that is presented while the worker is still busy with an
+/// earlier frame are queued and sent late rather than dropped.
+#[cfg(not(target_arch = "wasm32"))]
+struct TriggerWorker {
+    sender: std::sync::mpsc::Sender<TriggerJob>,
+    /// Number of jobs that have been handed to the worker but not finished.
+    pending: Arc<AtomicUsize>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TriggerWorker {
+    /// Start the worker thread for the given window.
+    fn new(window: &Window) -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel::<TriggerJob>();
+        let pending = Arc::new(AtomicUsize::new(0));
+
+        let window = window.clone();
+        let pending_clone = pending.clone();
+        std::thread::spawn(move || {
+            for job in receiver {
+                if let Some(write_time) = job.reset_after {
+                    reset_trigger_after_pulse(&window, write_time, job.frame_index);
+                }
+
+                if job.delayed {
+                    log::warn!("The triggers of frame {} are sent {:.1} ms after the flip, as the triggers of an earlier frame were still being sent",
+                               job.frame_index,
+                               Timestamp::now().duration_since(job.flip_time).as_secs_f64() * 1000.0);
+                }
+
+                for &code in &job.codes {
+                    let write_time = write_trigger(&window, &mut window.trigger_output.lock_blocking(), code, job.frame_index, job.flip_time);
+                    if let Some(write_time) = write_time {
+                        reset_trigger_after_pulse(&window, write_time, job.frame_index);
+                    }
+                }
+
+                pending_clone.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+
+        Self { sender, pending }
+    }
+
+    /// Sends the trigger codes of a presented frame and records them with the
+    /// flip time of the frame.
+    fn send(&self, window: &Window, codes: &[u8], frame_index: u64, flip_time: web_time::Instant) {
+        let flip_time = Timestamp::from(flip_time);
+
+        // if the worker is idle, the first code can be written right away
+        let mut job = TriggerJob { codes: codes.to_vec(),
+                                   frame_index,
+                                   flip_time,
+                                   reset_after: None,
+                                   delayed: true };
+        if self.pending.load(Ordering::SeqCst) == 0 {
+            let mut output = window.trigger_output.lock_blocking();
+
+            if output.as_ref().and_then(|output| output.pulse_width()).is_none() {
+                // codes that are not reset can all be written right away
+                for &code in codes {
+                    write_trigger(window, &mut output, code, frame_index, flip_time);
+                }
+                return;
+            }
+
+            job.reset_after = write_trigger(window, &mut output, job.codes.remove(0), frame_index, flip_time);
+            job.delayed = false;
+        }
+
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.sender.send(job).is_err() {
+            log::error!("Failed to send the triggers of frame {}: the trigger worker has stopped", frame_index);
+        }
+    }
+}
+
+/// Waits until the pulse width of the trigger output has passed since a code
+/// was written at `write_time`, then resets the output to 0.
+#[cfg(not(target_arch = "wasm32"))]
+fn reset_trigger_after_pulse(window: &Window, write_time: Timestamp, frame_index: u64) {
+    let pulse_width = window.trigger_output.lock_blocking().as_ref().and_then(|output| output.pulse_width());
+
+    if let Some(pulse_width) = pulse_width {
+        sleep_until((write_time + pulse_width).instant());
+
+        if let Some(Err(e)) = window.trigger_output.lock_blocking().as_mut().map(|output| output.write(0)) {
+            log::error!("Failed to reset trigger output after frame {}: {}", frame_index, e);
         }
     }
 }
 
+/// Writes a single trigger code of a presented frame to the output and records
+/// it. Returns the time the code was written, or None if there is no output or
+/// writing failed (in which case the code is recorded without a write time).
+#[cfg(not(target_arch = "wasm32"))]
+fn write_trigger(window: &Window, output: &mut Option<Box<dyn TriggerOutput>>, code: u8, frame_index: u64, flip_time: Timestamp) -> Option<Timestamp> {
+    let write_time = match output.as_mut().map(|output| output.write(code)) {
+        Some(Ok(write_time)) => Some(write_time),
+        Some(Err(e)) => {
+            log::error!("Failed to send trigger {} for frame {}: {}", code, frame_index, e);
+            None
+        }
+        None => None,
+    };
+
+    window.sent_triggers.lock_blocking().push(SentTrigger { frame_index,
+                                                            code,
+                                                            flip_time,
+                                                            write_time });
+    log::info!("Trigger {} for frame {} presented at {:.6} s", code, frame_index, window.clock.secs(flip_time));
+
+    write_time
+}
+
 /// A frame is a collection of renderables that will be rendered together.
 /// Rendering is lazy, i.e. the prepare() and render() functions of the
 /// renderables will only be called once the frame is submitted to the render
@@ -488,6 +1242,18 @@ pub struct Frame {
     pub stimuli: Arc<Mutex<Vec<Box<dyn Stimulus>>>>,
     color_format: ColorFormat,
     pub bg_color: super::color::RawRgba,
+    /// The window this frame was created for.
+    #[dbg(placeholder = "...")]
+    window: Window,
+    /// The photodiode marker of the window (if enabled).
+    #[dbg(placeholder = "...")]
+    photodiode_marker: Option<PhotodiodeMarker>,
+    /// Whether the photodiode marker is switched on for this frame.
+    marked: bool,
+    /// Trigger codes that are sent when the frame is presented.
+    triggers: Vec<u8>,
+    /// The time the frame was passed to `Window::present()`.
+    submit_time: Option<web_time::Instant>,
 }
 
 impl Frame {
@@ -495,6 +1261,31 @@ impl Frame {
     pub fn set_bg_color(&mut self, bg_color: impl IntoColor<palette::Xyza<palette::white_point::D65, f32>>) {
         self.bg_color = self.color_format.convert_to_raw_rgba(bg_color);
     }
+
+    /// Marks the frame, e.g. because it contains the onset of a stimulus. On
+    /// marked frames, the photodiode marker of the window (see
+    /// `Window::set_photodiode_marker()`) is switched on.
+    pub fn mark(&mut self) {
+        self.marked = true;
+    }
+
+    /// Returns true if the frame has been marked.
+    pub fn is_marked(&self) -> bool {
+        self.marked
+    }
+
+    /// Adds a trigger code that is sent to the window's trigger output (see
+    /// `Window::set_trigger_output()`) right after the frame has been
+    /// presented. Codes are sent in the order they were added, and recorded
+    /// with the flip time of the frame (see `Window::sent_triggers()`).
+    pub fn add_trigger(&mut self, code: u8) {
+        self.triggers.push(code);
+    }
+
+    /// Returns the trigger codes that will be sent when the frame is presented.
+    pub fn triggers(&self) -> &[u8] {
+        &self.triggers
+    }
 }
 
 impl Frame {
@@ -504,6 +1295,12 @@ impl Frame {
         for renderable in &mut self.stimuli.lock().await.iter_mut() {
             renderable.prepare(window, window_state, gpu_state);
         }
+
+        // the photodiode marker is prepared last, so that it is drawn on top
+        if let Some(marker) = &mut self.photodiode_marker {
+            marker.set_marked(self.marked);
+            marker.stimulus.prepare(window, window_state, gpu_state);
+        }
     }
 
     fn render(&mut self, enc: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) -> () {
@@ -514,8 +1311,89 @@ impl Frame {
         for renderable in lb.iter_mut() {
             renderable.render(enc, view);
         }
+
+        if let Some(marker) = &mut self.photodiode_marker {
+            marker.stimulus.render(enc, view);
+        }
         //log::info!("Time to render stimuli: {:?}", t_start.elapsed());
     }
+
+    /// Clears the view with the background color, then prepares and renders all
+    /// stimuli into it.
+    async fn prepare_and_render(&mut self,
+                                window: &Window,
+                                window_state: &InternalWindowState,
+                                gpu_state: &GPUState,
+                                encoder: &mut wgpu::CommandEncoder,
+                                view: &wgpu::TextureView)
+                                -> () {
+        // clear the frame
+        {
+            // clear the frame (once the lifetime annoyance is fixed, this can be removed
+            // only a single render pass is needed using the LoadOp::Clear
+            // option)
+            let _rpass =
+                &mut encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(self.bg_color.into()),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+        }
+
+        self.prepare(window, window_state, gpu_state).await;
+        self.render(encoder, view);
+    }
+
+    /// Renders the frame into a new texture and copies it back to the CPU,
+    /// without presenting it. The image has the same size as the window. This
+    /// is useful to produce figures or to test stimuli.
+    pub fn render_to_image(&mut self) -> Result<image::RgbaImage, PsybeeError> {
+        let window = self.window.clone();
+        let window_state = window.read_window_state_blocking();
+        let gpu_state = window.read_gpu_state_blocking();
+
+        let format = wgpu::TextureFormat::Bgra8Unorm;
+        let texture = gpu_state.device.create_texture(&wgpu::TextureDescriptor { label: Some("frame_capture_texture"),
+                                                                                  size: wgpu::Extent3d { width: window_state.config.width,
+                                                                                                         height: window_state.config.height,
+                                                                                                         depth_or_array_layers: 1 },
+                                                                                  mip_level_count: 1,
+                                                                                  sample_count: 1,
+                                                                                  dimension: wgpu::TextureDimension::D2,
+                                                                                  format,
+                                                                                  usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                                                                                  view_formats: &[format] });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor { format: Some(format),
+                                                                      ..wgpu::TextureViewDescriptor::default() });
+
+        let mut encoder = gpu_state.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        block_on(self.prepare_and_render(&window, &window_state, &gpu_state, &mut encoder, &view));
+
+        let buffer = copy_texture_to_buffer(&gpu_state.device, &mut encoder, &texture);
+
+        gpu_state.queue.submit(Some(encoder.finish()));
+
+        read_buffer_to_image(&gpu_state.device, &buffer, texture.width(), texture.height(), format)
+    }
+
+    /// Renders the frame (see `render_to_image()`) and saves it to the given
+    /// path. The image format is derived from the file extension (e.g. PNG).
+    pub fn render_to_file(&mut self, path: impl AsRef<std::path::Path>) -> Result<(), PsybeeError> {
+        let image = self.render_to_image()?;
+        image.save(path)?;
+        Ok(())
+    }
 }
 
 impl Frame {
@@ -532,3 +1410,93 @@ impl Frame {
         }
     }
 }
+
+/// A handle to a pending frame capture, see `Window::capture_next_frame()`.
+#[derive(Debug)]
+pub struct FrameCapture {
+    receiver: Receiver<Result<image::RgbaImage, PsybeeError>>,
+}
+
+impl FrameCapture {
+    /// Blocks until the frame has been presented and returns the captured image.
+    pub fn wait(self) -> Result<image::RgbaImage, PsybeeError> {
+        block_on(self.receiver.recv()).map_err(|_| PsybeeError::FrameCaptureError("the render task stopped before the frame was captured".to_string()))?
+    }
+
+    /// Returns the captured image if the frame has already been presented, or
+    /// None otherwise.
+    pub fn try_get(&self) -> Option<Result<image::RgbaImage, PsybeeError>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Encodes a copy of the given texture into a new buffer that can be mapped for
+/// reading. Rows are padded to satisfy wgpu's alignment requirements.
+fn copy_texture_to_buffer(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) -> wgpu::Buffer {
+    let size = texture.size();
+    let padded_bytes_per_row = padded_bytes_per_row(size.width);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor { label: Some("frame_capture_buffer"),
+                                                                size: (padded_bytes_per_row * size.height) as u64,
+                                                                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                                                                mapped_at_creation: false });
+
+    encoder.copy_texture_to_buffer(texture.as_image_copy(),
+                                   wgpu::ImageCopyBuffer { buffer: &buffer,
+                                                           layout: wgpu::ImageDataLayout { offset: 0,
+                                                                                           bytes_per_row: Some(padded_bytes_per_row),
+                                                                                           rows_per_image: Some(size.height) } },
+                                   wgpu::Extent3d { width: size.width,
+                                                    height: size.height,
+                                                    depth_or_array_layers: 1 });
+
+    buffer
+}
+
+/// Maps a buffer filled by `copy_texture_to_buffer()` and converts it into an
+/// RGBA image. This blocks until the GPU has finished all submitted work.
+fn read_buffer_to_image(device: &wgpu::Device, buffer: &wgpu::Buffer, width: u32, height: u32, format: wgpu::TextureFormat) -> Result<image::RgbaImage, PsybeeError> {
+    let is_bgra = match format {
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        _ => return Err(PsybeeError::FrameCaptureError(format!("unsupported texture format {:?}", format))),
+    };
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = bounded(1);
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+             let _ = sender.try_send(result);
+         });
+    device.poll(wgpu::Maintain::Wait);
+
+    block_on(receiver.recv()).map_err(|_| PsybeeError::FrameCaptureError("buffer mapping was cancelled".to_string()))?
+                             .map_err(|e| PsybeeError::FrameCaptureError(e.to_string()))?;
+
+    let padded_bytes_per_row = padded_bytes_per_row(width) as usize;
+    let bytes_per_row = width as usize * 4;
+
+    let mut pixels = Vec::with_capacity(bytes_per_row * height as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row) {
+            pixels.extend_from_slice(&row[..bytes_per_row]);
+        }
+    }
+    buffer.unmap();
+
+    if is_bgra {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    Ok(image::RgbaImage::from_raw(width, height, pixels).expect("Buffer size does not match image size. This is likely a bug, please report it."))
+}
+
+/// Returns the number of bytes per row of an RGBA texture with the given width,
+/// padded to `wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`.
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let bytes_per_row = width * 4;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (bytes_per_row + align - 1) / align * align
+}
@@ -0,0 +1,84 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for clocks and timestamps.
+
+use std::time::Duration;
+
+use psybee::clock::{Clock, Timestamp};
+use psybee::utils::BIDSEventLogger;
+use psybee::MainLoop;
+
+#[test]
+fn clocks_measure_time_relative_to_their_zero() {
+    let clock = Clock::new();
+    let shared = clock.clone();
+
+    let before = Timestamp::now();
+    std::thread::sleep(Duration::from_millis(20));
+    let after = Timestamp::now();
+
+    assert!(after > before);
+    assert!(after.secs_since(before) >= 0.02);
+    assert_eq!(before.secs_since(after), -after.secs_since(before));
+
+    // subtracting more than the monotonic clock can represent does not panic
+    assert_eq!(after.checked_sub(Duration::from_millis(1)), Some(after - Duration::from_millis(1)));
+    assert_eq!(after.checked_sub(Duration::MAX), None);
+
+    // resetting a clock resets all of its clones
+    clock.reset_to(after);
+    assert_eq!(shared.secs(after), 0.0);
+    assert!(shared.secs(before) <= -0.02);
+    assert!(shared.elapsed() < Duration::from_millis(20));
+}
+
+#[test]
+fn sub_clocks_are_shared_by_name() {
+    let clock = Clock::new();
+    let trial = clock.sub_clock("trial");
+
+    std::thread::sleep(Duration::from_millis(10));
+    clock.sub_clock("trial").reset();
+    assert!(trial.elapsed() < Duration::from_millis(10));
+    assert!(clock.elapsed() >= Duration::from_millis(10));
+    assert_eq!(clock.sub_clock_names(), vec!["trial".to_string()]);
+}
+
+#[test]
+fn every_session_has_its_own_experiment_clock() {
+    let zero_of_session = || {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+        main_loop.run_experiment(move |em| {
+                     // the clock starts when the main loop is created, not when it is first used
+                     assert!(em.clock().elapsed_secs() > 0.0);
+                     sender.send(em.clock().zero()).unwrap();
+                     Ok(())
+                 });
+
+        receiver.recv().unwrap()
+    };
+
+    let first = zero_of_session();
+    std::thread::sleep(Duration::from_millis(20));
+    let second = zero_of_session();
+
+    assert!(second.duration_since(first) >= Duration::from_millis(20));
+}
+
+#[test]
+fn bids_onsets_are_measured_on_the_given_clock() {
+    let path = std::env::temp_dir().join(format!("psybee_clock_{}_events.tsv", std::process::id()));
+    let clock = Clock::new();
+    let mut logger = BIDSEventLogger::new(path.clone(), vec!["value"], true, clock.clone()).unwrap();
+
+    logger.log_at(("a",), clock.zero() + Duration::from_millis(1500), 0.5).unwrap();
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(log.lines().nth(1), Some("1.5\t0.5\ta"));
+}
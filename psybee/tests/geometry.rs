@@ -0,0 +1,140 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for shapes.
+
+use psybee::visual::geometry::{Annulus, Circle, Cross, Ellipse, Line, Polygon, Polyline, Rectangle, Size, ToVertices, Transformation2D};
+use psybee::visual::Window;
+use psybee::MainLoop;
+
+fn contains(window: &Window, shape: &dyn ToVertices, x: f64, y: f64) -> bool {
+    shape.contains(window, &Transformation2D::Identity, Size::Pixels(x), Size::Pixels(y))
+}
+
+#[test]
+fn shapes_contain_points_inside_their_outline() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(200, 200);
+
+                 let ellipse = Ellipse::new(0.0, 0.0, 40.0, 20.0);
+                 assert!(contains(&window, &ellipse, 35.0, 0.0));
+                 assert!(!contains(&window, &ellipse, 0.0, 25.0));
+
+                 let annulus = Annulus::new(0.0, 0.0, 10.0, 20.0);
+                 assert!(contains(&window, &annulus, 15.0, 0.0));
+                 assert!(!contains(&window, &annulus, 5.0, 0.0));
+                 assert!(!contains(&window, &annulus, 25.0, 0.0));
+
+                 // thickness can be given in any unit (1% of the window width = 2 px)
+                 let line = Line::new(-50.0, 0.0, 50.0, 0.0, Size::ScreenWidth(0.01));
+                 assert!(contains(&window, &line, 20.0, 0.9));
+                 assert!(!contains(&window, &line, 20.0, 1.5));
+                 assert!(!contains(&window, &line, 55.0, 0.0));
+
+                 let cross = Cross::new(0.0, 0.0, 40.0, 4.0);
+                 assert!(contains(&window, &cross, 18.0, 0.0));
+                 assert!(contains(&window, &cross, 0.0, -18.0));
+                 assert!(!contains(&window, &cross, 10.0, 10.0));
+
+                 // the bars do not overlap, i.e. the triangles cover the cross exactly
+                 // (area 2 * 40 * 4 - 4 * 4 = 304)
+                 let vertices = cross.to_vertices_px(1.0, 1.0, 200, 200);
+                 let floats: &[f32] = bytemuck::cast_slice(&vertices);
+                 let area: f32 = floats.chunks_exact(24)
+                                       .map(|t| ((t[8] - t[0]) * (t[17] - t[1]) - (t[9] - t[1]) * (t[16] - t[0])).abs() / 2.0)
+                                       .sum();
+                 assert!((area - 304.0).abs() < 1e-3);
+
+                 Ok(())
+             });
+}
+
+#[test]
+fn concave_shapes_are_triangulated_and_hit_tested() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(200, 200);
+
+                 // an L-shape, given clockwise
+                 let l_shape = Polygon::new(vec![(0.0, 0.0), (0.0, 40.0), (10.0, 40.0), (10.0, 10.0), (30.0, 10.0), (30.0, 0.0)]);
+                 assert_eq!(l_shape.n_vertices(), 3 * 4);
+                 assert!(contains(&window, &l_shape, 5.0, 30.0));
+                 assert!(contains(&window, &l_shape, 25.0, 5.0));
+                 assert!(!contains(&window, &l_shape, 20.0, 20.0));
+
+                 // the triangles cover the L-shape exactly (area 40 * 10 + 20 * 10 = 600)
+                 let vertices = l_shape.to_vertices_px(1.0, 1.0, 200, 200);
+                 // each vertex is 8 floats, starting with its position
+                 let floats: &[f32] = bytemuck::cast_slice(&vertices);
+                 let area: f32 = floats.chunks_exact(24)
+                                       .map(|t| ((t[8] - t[0]) * (t[17] - t[1]) - (t[9] - t[1]) * (t[16] - t[0])).abs() / 2.0)
+                                       .sum();
+                 assert!((area - 600.0).abs() < 1e-3);
+
+                 // a polyline with a right-angled corner
+                 let polyline = Polyline::new(vec![(0.0, 0.0), (40.0, 0.0), (40.0, 40.0)], 4.0);
+                 assert!(contains(&window, &polyline, 20.0, 1.5));
+                 assert!(contains(&window, &polyline, 41.5, -1.5));
+                 assert!(!contains(&window, &polyline, 20.0, 20.0));
+
+                 // a hairpin is bevelled rather than thinned at the corner, so points
+                 // just within half the thickness of either segment are contained
+                 let hairpin = Polyline::new(vec![(0.0, 0.0), (40.0, 0.0), (0.0, 4.0)], 4.0);
+                 assert_eq!(hairpin.n_vertices(), polyline.n_vertices());
+                 assert!(contains(&window, &hairpin, 39.0, -1.99));
+                 assert!(contains(&window, &hairpin, 40.0, 1.99));
+                 assert!(!contains(&window, &hairpin, 43.0, 0.0));
+
+                 // points are mapped into the coordinate system of the shape
+                 let moved = Transformation2D::translation(100.0, 0.0);
+                 assert!(l_shape.contains(&window, &moved, Size::Pixels(105.0), Size::Pixels(30.0)));
+                 assert!(!l_shape.contains(&window, &moved, Size::Pixels(5.0), Size::Pixels(30.0)));
+
+                 Ok(())
+             });
+}
+
+#[test]
+fn transformed_shapes_are_hit_tested_in_their_own_coordinates() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(400, 400);
+                 let point = |x: f64, y: f64| (Size::Pixels(x), Size::Pixels(y));
+
+                 // rotating by 90 degrees around the origin turns the wide rectangle into a tall one
+                 let rectangle = Rectangle::new(0.0, 0.0, 100.0, 20.0);
+                 let rotation = Transformation2D::RotationPoint(90.0, Size::Pixels(0.0), Size::Pixels(0.0));
+                 let (x, y) = point(10.0, -50.0);
+                 assert!(rectangle.contains(&window, &rotation, x, y));
+                 let (x, y) = point(50.0, 10.0);
+                 assert!(!rectangle.contains(&window, &rotation, x, y));
+
+                 // shearing moves the right end of the rectangle upwards (y' = y + x)
+                 let shear = Transformation2D::ShearPoint(1.0, 0.0, Size::Pixels(0.0), Size::Pixels(0.0));
+                 let (x, y) = point(50.0, 60.0);
+                 assert!(rectangle.contains(&window, &shear, x, y));
+                 let (x, y) = point(50.0, 10.0);
+                 assert!(!rectangle.contains(&window, &shear, x, y));
+
+                 // scaling a circle non-uniformly turns it into an ellipse
+                 let circle = Circle::new(0.0, 0.0, 20.0);
+                 let scale = Transformation2D::ScalePoint(2.0, 0.5, Size::Pixels(0.0), Size::Pixels(0.0));
+                 let (x, y) = point(30.0, 0.0);
+                 assert!(circle.contains(&window, &scale, x, y));
+                 let (x, y) = point(0.0, 15.0);
+                 assert!(!circle.contains(&window, &scale, x, y));
+
+                 // nothing is hit if the transformation cannot be inverted
+                 let collapse = Transformation2D::ScalePoint(0.0, 1.0, Size::Pixels(0.0), Size::Pixels(0.0));
+                 assert!(!circle.contains(&window, &collapse, Size::Pixels(0.0), Size::Pixels(0.0)));
+
+                 Ok(())
+             });
+}
@@ -0,0 +1,18 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for running experiments headless.
+
+use psybee::errors::PsybeeError;
+use psybee::MainLoop;
+
+#[test]
+#[should_panic(expected = "Experiment failed")]
+fn failing_experiments_panic_instead_of_exiting() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|_| Err(PsybeeError::CustomError("failed on purpose".to_string())));
+}
@@ -0,0 +1,43 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for input events.
+
+use psybee::clock::Timestamp;
+use psybee::input::{Event, Key, KeyCombo, LogicalKey, Modifiers};
+
+fn key_press(key: &str, key_code: Key, modifiers: Modifiers, repeat: bool) -> Event {
+    Event::KeyPress { timestamp: Timestamp::now(),
+                      key: key.to_string(),
+                      code: 0,
+                      key_code: Some(key_code),
+                      logical_key: LogicalKey::Character(key.into()),
+                      modifiers,
+                      repeat }
+}
+
+#[test]
+fn key_codes_do_not_depend_on_the_layout() {
+    // the key labelled "z" on a German keyboard is in the position of "y" on a US keyboard
+    let event = key_press("z", Key::KeyY, Modifiers::empty(), false);
+
+    assert!(event.key_pressed("z"));
+    assert!(event.key_code_pressed(Key::KeyY));
+    assert!(!event.key_code_pressed(Key::KeyZ));
+    assert!(!event.key_code_released(Key::KeyY));
+}
+
+#[test]
+fn key_combos_match_modifiers_exactly() {
+    let combo = KeyCombo::new(Key::KeyQ, Modifiers::CONTROL | Modifiers::SHIFT);
+
+    assert!(combo.matches(&key_press("Q", Key::KeyQ, Modifiers::CONTROL | Modifiers::SHIFT, false)));
+    assert!(!combo.matches(&key_press("q", Key::KeyQ, Modifiers::CONTROL, false)));
+    assert!(!combo.matches(&key_press("Q", Key::KeyQ, Modifiers::CONTROL | Modifiers::SHIFT, true)));
+
+    // the default abort key is Escape without modifiers
+    assert_eq!(KeyCombo::default(), KeyCombo::from(Key::Escape));
+}
@@ -0,0 +1,27 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for LSL marker outlets.
+
+#![cfg(feature = "lsl")]
+
+use std::time::Duration;
+
+use psybee::clock::Timestamp;
+use psybee::lsl::LslMarkerOutlet;
+use psybee::triggers::TriggerOutput;
+
+#[test]
+fn markers_can_be_pushed_without_consumers() {
+    let mut outlet = LslMarkerOutlet::new("psybee-test-markers", "psybee-test").unwrap();
+    assert_eq!(outlet.name(), "psybee-test-markers");
+    assert!(!outlet.has_consumers());
+
+    outlet.push_at("stimulus_onset", Timestamp::now() - Duration::from_millis(100)).unwrap();
+    outlet.push(42).unwrap();
+    outlet.log(("response", "left")).unwrap();
+    outlet.send(7).unwrap();
+}
@@ -0,0 +1,61 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for UDP marker streams.
+
+use std::time::Duration;
+
+use psybee::clock::{Clock, Timestamp};
+use psybee::markers::{Marker, UdpMarkerReceiver, UdpMarkerSender};
+use psybee::triggers::TriggerOutput;
+
+const TIMEOUT: Option<Duration> = Some(Duration::from_secs(5));
+
+#[test]
+fn markers_are_received_over_loopback() {
+    let receiver = UdpMarkerReceiver::bind("127.0.0.1:0").unwrap();
+    let mut sender = UdpMarkerSender::new("test-markers", receiver.local_addr().unwrap(), Clock::new()).unwrap();
+
+    let onset = Timestamp::now() - Duration::from_millis(100);
+    sender.push_at("stimulus\tface", onset).unwrap();
+    sender.push(42).unwrap();
+    sender.log(("response", "left")).unwrap();
+    sender.send(7).unwrap();
+
+    let first = receiver.recv(TIMEOUT).unwrap().unwrap();
+    assert_eq!(first.stream, "test-markers");
+    assert_eq!(first.sequence, 0);
+    assert_eq!(first.marker, Marker::String("stimulus\tface".to_string()));
+    assert!((first.sender_time - sender.clock().secs(onset)).abs() < 1e-6);
+    // the time is mapped to the receiver's clock (which is the same clock here)
+    assert!(first.timestamp.secs_since(onset).abs() < 0.05);
+
+    let markers: Vec<Marker> = (0..3).map(|_| receiver.recv(TIMEOUT).unwrap().unwrap().marker).collect();
+    assert_eq!(markers,
+               vec![Marker::Int(42), Marker::String("response\tleft".to_string()), Marker::Int(7)]);
+}
+
+#[test]
+fn receiving_times_out_without_markers() {
+    let receiver = UdpMarkerReceiver::bind("127.0.0.1:0").unwrap();
+
+    assert_eq!(receiver.recv(Some(Duration::from_millis(10))).unwrap(), None);
+}
+
+#[test]
+fn markers_older_than_the_receiver_clock_are_timestamped_on_arrival() {
+    let receiver = UdpMarkerReceiver::bind("127.0.0.1:0").unwrap();
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    // a marker that claims to have occurred long before it was sent
+    let datagram = "PSYBEE-MARKER\t1\tforged\t0\t0\t1e9\ti\t1";
+    let before = Timestamp::now();
+    socket.send_to(datagram.as_bytes(), receiver.local_addr().unwrap()).unwrap();
+
+    let marker = receiver.recv(TIMEOUT).unwrap().unwrap();
+    assert_eq!(marker.marker, Marker::Int(1));
+    assert!(marker.timestamp >= before);
+}
@@ -0,0 +1,188 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Golden-image regression tests for all fill patterns.
+//!
+//! Each test renders a pattern through `PatternStimulus` into an offscreen
+//! window on the fallback (software) adapter and compares the result against a
+//! reference image in `tests/reference`. To create or update the reference
+//! images after an intentional change, run the tests with `PSYBEE_BLESS=1` and
+//! check the new images in.
+
+use std::path::PathBuf;
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use psybee::visual::color::SRGBA;
+use psybee::visual::geometry::{Rectangle, Size};
+use psybee::visual::stimuli::pattern_stimulus::FillPattern;
+use psybee::visual::stimuli::patterns::{Checkerboard, Gabor, GaborPatch, Image, Sprite, Uniform};
+use psybee::visual::stimuli::PatternStimulus;
+use psybee::visual::Window;
+use psybee::MainLoop;
+
+/// Size of the offscreen window in pixels.
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 128;
+
+/// Maximum difference per channel for two pixels to be considered equal.
+/// Software rasterizers are not required to be bit-exact, so we allow for
+/// small rounding differences.
+const CHANNEL_TOLERANCE: u8 = 3;
+
+/// Maximum fraction of pixels that may exceed the channel tolerance (e.g. due
+/// to slightly different rasterization of edges).
+const MAX_MISMATCH_FRACTION: f64 = 0.005;
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
+                                             .join("reference")
+                                             .join(format!("{}.png", name))
+}
+
+/// Compares the image against the stored reference image, or stores it as the
+/// new reference if `PSYBEE_BLESS` is set.
+fn compare_with_reference(name: &str, image: &RgbaImage) {
+    let path = reference_path(name);
+
+    if std::env::var_os("PSYBEE_BLESS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image.save(&path).unwrap();
+        return;
+    }
+
+    let reference = match image::open(&path) {
+        Ok(reference) => reference.to_rgba8(),
+        Err(e) => panic!("Could not load reference image {:?} ({}). Run the tests with PSYBEE_BLESS=1 to create it.", path, e),
+    };
+
+    assert_eq!(reference.dimensions(), image.dimensions(), "Dimensions of {} do not match the reference image", name);
+
+    let mismatched = reference.pixels()
+                              .zip(image.pixels())
+                              .filter(|(a, b)| a.0.iter().zip(b.0.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+                              .count();
+
+    let fraction = mismatched as f64 / (WIDTH * HEIGHT) as f64;
+
+    if fraction > MAX_MISMATCH_FRACTION {
+        // save the actual image next to the reference to make debugging easier
+        let actual_path = path.with_extension("actual.png");
+        let _ = image.save(&actual_path);
+
+        panic!("{} differs from the reference image: {} of {} pixels do not match (actual image saved to {:?})",
+               name,
+               mismatched,
+               WIDTH * HEIGHT,
+               actual_path);
+    }
+}
+
+/// Renders a full-screen `PatternStimulus` with the given pattern into an
+/// offscreen window and compares it against the reference image.
+fn check_pattern<P, F>(name: &'static str, make_pattern: F)
+    where P: FillPattern + 'static,
+          F: FnOnce(&Window) -> P + Send + 'static
+{
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(move |em| {
+                 let window = em.create_offscreen_window(WIDTH, HEIGHT);
+
+                 let pattern = make_pattern(&window);
+                 let stimulus = PatternStimulus::new_from_pattern(&window, Rectangle::FULLSCREEN, pattern);
+
+                 let mut frame = window.get_frame();
+                 frame.add(Box::new(stimulus));
+
+                 let image = frame.render_to_image()?;
+                 compare_with_reference(name, &image);
+
+                 Ok(())
+             });
+}
+
+/// A test image with a horizontal red and a vertical green gradient.
+fn gradient_image() -> DynamicImage {
+    let image = RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, 128, 255]));
+    DynamicImage::ImageRgba8(image)
+}
+
+#[test]
+fn uniform() {
+    check_pattern("uniform", |_| Uniform::new(SRGBA::new(0.2, 0.4, 0.8, 1.0)));
+}
+
+#[test]
+fn checkerboard() {
+    check_pattern("checkerboard", |_| {
+        Checkerboard::new((0.0, 0.0),
+                          (Size::Pixels(32.0), Size::Pixels(32.0)),
+                          SRGBA::new(1.0, 1.0, 1.0, 1.0),
+                          SRGBA::new(0.0, 0.0, 0.0, 1.0))
+    });
+}
+
+#[test]
+fn gabor() {
+    check_pattern("gabor", |_| {
+        Gabor::new(0.0,
+                   Size::Pixels(20.0),
+                   Size::Pixels(20.0),
+                   Size::Pixels(20.0),
+                   0.25 * std::f32::consts::PI,
+                   SRGBA::new(1.0, 1.0, 1.0, 1.0))
+    });
+}
+
+#[test]
+fn gabor_patch() {
+    check_pattern("gabor_patch", |_| {
+        GaborPatch::new(0.0,
+                        Size::Pixels(20.0),
+                        SRGBA::new(1.0, 1.0, 1.0, 1.0),
+                        (Size::Pixels(0.0), Size::Pixels(0.0)),
+                        (Size::Pixels(20.0), Size::Pixels(20.0)))
+    });
+}
+
+#[test]
+fn image() {
+    check_pattern("image", |_| Image::new(gradient_image()));
+}
+
+#[test]
+fn sprite() {
+    // without an fps, the sprite stays on its first image, which keeps the output
+    // deterministic
+    check_pattern("sprite", |_| {
+        let second = DynamicImage::ImageRgba8(RgbaImage::from_pixel(64, 64, Rgba([255, 0, 0, 255])));
+        Sprite::new(vec![gradient_image(), second], None, None).unwrap()
+    });
+}
+
+#[test]
+fn captured_frame_matches_rendered_frame() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(WIDTH, HEIGHT);
+
+                 let stimulus = PatternStimulus::new_from_pattern(&window, Rectangle::FULLSCREEN, Image::new(gradient_image()));
+
+                 let mut frame = window.get_frame();
+                 frame.add(Box::new(stimulus));
+
+                 let rendered = frame.render_to_image()?;
+
+                 let capture = window.capture_next_frame();
+                 window.present(frame);
+                 let captured = capture.wait()?;
+
+                 assert_eq!(rendered, captured);
+
+                 Ok(())
+             });
+}
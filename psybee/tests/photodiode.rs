@@ -0,0 +1,50 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for the photodiode marker.
+
+use psybee::visual::color::RawRgba;
+use psybee::visual::geometry::Size;
+use psybee::visual::photodiode::{Corner, PhotodiodeMarkerOptions};
+use psybee::MainLoop;
+
+#[test]
+fn marker_is_drawn_in_the_corner_and_switched_on_marked_frames() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+
+                 window.set_photodiode_marker(Some(PhotodiodeMarkerOptions { corner: Corner::TopRight,
+                                                                             size: Size::Pixels(20.0),
+                                                                             on_color: RawRgba::new(1.0, 1.0, 1.0, 1.0),
+                                                                             off_color: RawRgba::new(0.0, 0.0, 1.0, 1.0) }));
+
+                 let mut frame = window.get_frame();
+                 frame.set_bg_color(psybee::visual::color::GRAY);
+
+                 // unmarked frame: the marker shows the off colour
+                 let image = frame.render_to_image()?;
+                 assert_eq!(image.get_pixel(95, 5).0, [0, 0, 255, 255]);
+                 // the rest of the frame is not affected
+                 assert_eq!(image.get_pixel(5, 5).0, image.get_pixel(95, 95).0);
+                 assert_ne!(image.get_pixel(75, 5).0, [0, 0, 255, 255]);
+
+                 // marked frame: the marker shows the on colour
+                 frame.mark();
+                 let image = frame.render_to_image()?;
+                 assert_eq!(image.get_pixel(95, 5).0, [255, 255, 255, 255]);
+
+                 // disabling the marker removes it from new frames
+                 window.set_photodiode_marker(None);
+                 let mut frame = window.get_frame();
+                 frame.mark();
+                 let image = frame.render_to_image()?;
+                 assert_eq!(image.get_pixel(95, 5).0, [0, 0, 0, 255]);
+
+                 Ok(())
+             });
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for presenting frames.
+
+use std::time::Duration;
+
+use psybee::MainLoop;
+
+#[test]
+fn blocking_presentation_returns_the_info_of_the_presented_frame() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+
+                 for expected_index in 0..3 {
+                     let before = web_time::Instant::now();
+                     let info = window.present(window.get_frame());
+
+                     assert_eq!(info.frame_index, expected_index);
+                     // the submit time is the time `present()` was called
+                     assert!(info.submit_time >= before);
+                     assert!(info.flip_time.is_some_and(|flip_time| flip_time >= info.submit_time));
+                 }
+
+                 Ok(())
+             });
+}
+
+#[test]
+fn frames_are_presented_for_the_requested_number_of_refresh_cycles() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+                 let refresh_interval = 1.0 / window.refresh_rate();
+
+                 let onset = window.present_for(window.get_frame(), 3);
+                 let last = window.last_presentation_info().unwrap();
+
+                 // the frame is visible from its onset until the end of the third refresh cycle
+                 let visible_for = last.flip_time.unwrap().duration_since(onset.flip_time.unwrap()).as_secs_f64();
+                 assert!(visible_for >= 1.5 * refresh_interval, "frame was visible for {} s", visible_for);
+
+                 // presenting for a single refresh cycle presents the frame once
+                 let onset = window.present_for(window.get_frame(), 1);
+                 assert_eq!(window.last_presentation_info().unwrap().frame_index, onset.frame_index);
+
+                 Ok(())
+             });
+}
+
+#[test]
+#[should_panic]
+fn frames_cannot_be_presented_for_zero_refresh_cycles() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+                 window.present_for(window.get_frame(), 0);
+                 Ok(())
+             });
+}
+
+#[test]
+fn frames_are_presented_at_the_vertical_blank_closest_to_the_deadline() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+                 let refresh_interval = Duration::from_secs_f64(1.0 / window.refresh_rate());
+
+                 window.present(window.get_frame());
+
+                 // the frame is held back until shortly before the deadline
+                 let deadline = web_time::Instant::now() + Duration::from_millis(100);
+                 let info = window.present_at(window.get_frame(), deadline);
+                 assert!(info.submit_time + refresh_interval >= deadline);
+                 assert!(info.flip_time.unwrap() + refresh_interval >= deadline);
+
+                 // a deadline that has already passed presents the frame immediately
+                 let before = web_time::Instant::now();
+                 let info = window.present_at(window.get_frame(), before - Duration::from_millis(100));
+                 assert!(info.submit_time.duration_since(before) < Duration::from_millis(100));
+
+                 Ok(())
+             });
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for psychometric function fitting.
+
+use psybee::psychometric::{fit, load_responses, FitOptions, PsychometricData, PsychometricFunction};
+
+/// Noise-free data generated from the given function.
+fn expected_data(function: PsychometricFunction, threshold: f64, slope: f64, intensities: &[f64]) -> Vec<PsychometricData> {
+    intensities.iter()
+               .map(|&intensity| {
+                   let p = 0.5 + 0.5 * function.eval(intensity, threshold, slope);
+                   PsychometricData { intensity,
+                                      n_trials: 1000,
+                                      n_correct: (p * 1000.0).round() as u32 }
+               })
+               .collect()
+}
+
+#[test]
+fn recovers_parameters_of_all_functions() {
+    let cases = [(PsychometricFunction::CumulativeGaussian, 0.0, 2.0, vec![-1.5, -1.0, -0.5, 0.0, 0.5, 1.0, 1.5]),
+                 (PsychometricFunction::Logistic, 3.0, 1.5, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+                 (PsychometricFunction::Weibull, 0.2, 3.5, vec![0.05, 0.1, 0.15, 0.2, 0.25, 0.3, 0.4])];
+
+    for (function, threshold, slope, intensities) in cases {
+        let data = expected_data(function, threshold, slope, &intensities);
+        let result = fit(&data,
+                         &FitOptions { function,
+                                       lapse_rate: Some(0.0),
+                                       ..Default::default() }).unwrap();
+
+        assert!((result.threshold - threshold).abs() < 0.02, "{:?}: threshold {}", function, result.threshold);
+        assert!((result.slope / slope - 1.0).abs() < 0.05, "{:?}: slope {}", function, result.slope);
+        assert!((result.eval(threshold) - (0.5 + 0.5 * function.eval(threshold, threshold, slope))).abs() < 0.01);
+
+        // intensity_at() is the inverse of eval()
+        let x = intensities[4];
+        assert!((result.intensity_at(result.eval(x)).unwrap() - x).abs() < 1e-3, "{:?}: inverse", function);
+    }
+}
+
+#[test]
+fn bootstrap_confidence_interval_contains_the_estimate() {
+    let data = expected_data(PsychometricFunction::Logistic, 1.0, 4.0, &[0.0, 0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 2.0]);
+    let data = data.into_iter()
+                   .map(|d| PsychometricData { n_trials: 40,
+                                               n_correct: (d.proportion_correct() * 40.0).round() as u32,
+                                               ..d })
+                   .collect::<Vec<_>>();
+
+    let options = FitOptions { function: PsychometricFunction::Logistic,
+                               n_bootstrap: 200,
+                               seed: Some(1),
+                               ..Default::default() };
+    let result = fit(&data, &options).unwrap();
+
+    let (lower, upper) = result.threshold_ci.unwrap();
+    assert!(lower < result.threshold && result.threshold < upper);
+    assert!(result.lapse_rate >= 0.0 && result.lapse_rate <= options.max_lapse_rate);
+
+    // the same seed gives the same intervals
+    assert_eq!(fit(&data, &options).unwrap().threshold_ci, result.threshold_ci);
+}
+
+#[test]
+fn loads_responses_from_event_logs() {
+    let path = std::env::temp_dir().join(format!("psybee_psychometric_{}_events.tsv", std::process::id()));
+    std::fs::write(&path, "onset\tduration\tcontrast\tcorrect\n0.0\t0.1\t0.5\t1\n1.0\t0.1\t0.5\t0\n2.0\t0\t\t\n3.0\t0.1\t0.1\ttrue\n").unwrap();
+
+    let data = load_responses(&path, "contrast", "correct").unwrap();
+    assert_eq!(data,
+               vec![PsychometricData { intensity: 0.1,
+                                       n_trials: 1,
+                                       n_correct: 1 },
+                    PsychometricData { intensity: 0.5,
+                                       n_trials: 2,
+                                       n_correct: 1 }]);
+}
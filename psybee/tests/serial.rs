@@ -0,0 +1,84 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for serial port devices, using a pseudo-terminal pair in place of
+//! real hardware.
+
+#![cfg(all(feature = "serial", unix))]
+
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use psybee::input::{Event, EventKind};
+use psybee::serial::{SerialResponseBox, TriggerPort};
+use psybee::MainLoop;
+use serialport::{SerialPort, TTYPort};
+
+#[test]
+fn trigger_port_sends_codes() {
+    let (master, mut slave) = TTYPort::pair().unwrap();
+    slave.set_timeout(Duration::from_secs(1)).unwrap();
+
+    let mut triggers = TriggerPort::from_port(Box::new(master));
+    triggers.send(7).unwrap();
+    triggers.pulse(42, Duration::from_millis(1)).unwrap();
+
+    let mut received = [0u8; 3];
+    slave.read_exact(&mut received).unwrap();
+    assert_eq!(received, [7, 42, 0]);
+}
+
+#[test]
+fn response_box_emits_timestamped_events() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(10, 10);
+                 let (mut master, slave) = TTYPort::pair().unwrap();
+
+                 let mut receiver = window.create_event_receiver();
+                 let _response_box = SerialResponseBox::from_port(&window, Box::new(slave))?;
+
+                 let before = psybee::clock::Timestamp::now();
+                 master.write_all(&[3, 4]).unwrap();
+
+                 let mut bytes = vec![];
+                 while bytes.len() < 2 {
+                     let event = receiver.wait_for(&[EventKind::ResponseBoxInput], &[] as &[&str], Some(Duration::from_secs(2)))
+                                         .expect("no event from the response box");
+                     assert!(*event.timestamp() >= before);
+                     if let Event::ResponseBoxInput { byte, .. } = event {
+                         bytes.push(byte);
+                     }
+                 }
+                 assert_eq!(bytes, vec![3, 4]);
+
+                 Ok(())
+             });
+}
+
+#[test]
+fn response_box_stops_reading_when_disconnected() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(10, 10);
+                 let (master, slave) = TTYPort::pair().unwrap();
+
+                 let response_box = SerialResponseBox::from_port(&window, Box::new(slave))?;
+                 assert!(response_box.is_running());
+
+                 drop(master);
+
+                 let start = std::time::Instant::now();
+                 while response_box.is_running() {
+                     assert!(start.elapsed() < Duration::from_secs(2), "the reading thread did not exit");
+                     std::thread::sleep(Duration::from_millis(10));
+                 }
+
+                 Ok(())
+             });
+}
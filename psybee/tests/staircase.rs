@@ -0,0 +1,114 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for the adaptive procedures, using simulated observers.
+
+use psybee::staircase::{AdaptiveProcedure, InterleavedStaircases, Pest, PestOptions, Quest, QuestOptions, Staircase, StepType, UpDownOptions,
+                        UpDownStaircase};
+use rand::{Rng, SeedableRng};
+
+/// A simulated 2AFC observer with a Weibull psychometric function.
+fn observer(threshold: f64) -> impl FnMut(f64) -> bool {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    move |intensity| {
+        let p = 0.5 + 0.5 * (1.0 - (-(intensity / threshold).powf(3.5)).exp());
+        rng.gen_bool(p.clamp(0.0, 1.0))
+    }
+}
+
+fn run<P: AdaptiveProcedure>(procedure: &mut P, threshold: f64) {
+    let mut respond = observer(threshold);
+    while !procedure.is_finished() {
+        let intensity = procedure.next_intensity();
+        procedure.update(respond(intensity));
+    }
+}
+
+#[test]
+fn up_down_staircase_steps_and_reverses() {
+    let mut staircase = UpDownStaircase::new(UpDownOptions { start_intensity: 1.0,
+                                                             n_up: 1,
+                                                             n_down: 2,
+                                                             step_sizes: vec![0.2, 0.1],
+                                                             step_type: StepType::Linear,
+                                                             n_reversals: Some(2),
+                                                             n_estimate_reversals: Some(2),
+                                                             ..Default::default() }).unwrap();
+
+    staircase.update(true);
+    assert_eq!(staircase.next_intensity(), 1.0);
+    staircase.update(true);
+    assert!((staircase.next_intensity() - 0.8).abs() < 1e-9);
+    staircase.update(false);
+    // first reversal at 0.8, the step size is now 0.1
+    assert!((staircase.next_intensity() - 0.9).abs() < 1e-9);
+    staircase.update(true);
+    staircase.update(true);
+    assert!((staircase.next_intensity() - 0.8).abs() < 1e-9);
+
+    assert_eq!(staircase.reversals(), &[0.8, 0.9]);
+    assert!((staircase.threshold_estimate().unwrap() - 0.85).abs() < 1e-9);
+    assert!(staircase.is_finished());
+}
+
+#[test]
+fn procedures_converge_to_the_threshold() {
+    let threshold = 0.2;
+
+    let mut up_down = UpDownStaircase::new(UpDownOptions { start_intensity: 1.0,
+                                                           n_down: 3,
+                                                           step_sizes: vec![4.0, 2.0, 1.0],
+                                                           step_type: StepType::Db,
+                                                           n_reversals: Some(16),
+                                                           n_estimate_reversals: Some(10),
+                                                           ..Default::default() }).unwrap();
+    run(&mut up_down, threshold);
+
+    let mut quest = Quest::new(QuestOptions { threshold_guess: 0.5,
+                                              n_trials: Some(80),
+                                              ..Default::default() }).unwrap();
+    run(&mut quest, threshold);
+
+    let mut pest = Pest::new(PestOptions { start_intensity: 0.6,
+                                           start_step: 0.1,
+                                           min_step: 0.005,
+                                           max_step: 0.2,
+                                           min_intensity: Some(0.0),
+                                           n_trials: Some(400),
+                                           ..Default::default() }).unwrap();
+    run(&mut pest, threshold);
+
+    for estimate in [up_down.threshold_estimate(), quest.threshold_estimate(), pest.threshold_estimate()] {
+        let estimate = estimate.unwrap();
+        assert!(estimate > threshold * 0.6 && estimate < threshold * 1.6, "estimate {} too far from {}", estimate, threshold);
+    }
+}
+
+#[test]
+fn interleaved_state_round_trips() {
+    let procedures: Vec<Staircase> = vec![UpDownStaircase::new(Default::default()).unwrap().into(),
+                                          Quest::new(Default::default()).unwrap().into()];
+    let mut interleaved = InterleavedStaircases::new(procedures, 3).unwrap();
+
+    for _ in 0..10 {
+        interleaved.next_intensity().unwrap();
+        interleaved.update(true).unwrap();
+    }
+
+    // a restored copy makes the same choices as the original
+    let json = serde_json::to_string(&interleaved).unwrap();
+    let mut restored: InterleavedStaircases = serde_json::from_str(&json).unwrap();
+
+    for i in 0..10 {
+        let (index_a, intensity_a) = interleaved.next_intensity().unwrap();
+        let (index_b, intensity_b) = restored.next_intensity().unwrap();
+        assert_eq!(index_a, index_b);
+        // JSON does not round-trip floats exactly
+        assert!((intensity_a - intensity_b).abs() < 1e-12);
+        interleaved.update(i % 3 != 0).unwrap();
+        restored.update(i % 3 != 0).unwrap();
+    }
+}
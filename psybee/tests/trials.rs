@@ -0,0 +1,91 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for the trial handler.
+
+use psybee::trials::{load_conditions, TrialHandler, TrialOptions, TrialOrder};
+
+fn write_conditions(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("psybee_{}_{}.csv", name, std::process::id()));
+    std::fs::write(&path, "contrast,side\n0.1,left\n0.5,right\n0.9,left\n").unwrap();
+    path
+}
+
+#[test]
+fn trials_are_balanced_and_reproducible() {
+    let conditions = load_conditions(write_conditions("balanced"), b',').unwrap();
+    assert_eq!(conditions[1].get("side"), Some("right"));
+    assert_eq!(conditions[2].get_parsed::<f64>("contrast").unwrap(), 0.9);
+
+    let options = TrialOptions { n_repetitions: 2,
+                                 n_blocks: 2,
+                                 order: TrialOrder::RandomWithinRepetitions,
+                                 seed: Some(1) };
+
+    let handler = TrialHandler::new(conditions.clone(), options.clone()).unwrap();
+    assert_eq!(handler.n_trials(), 12);
+
+    // every condition occurs once per repetition
+    for chunk in handler.trials().chunks(3) {
+        let mut indices = chunk.iter().map(|t| t.condition_index).collect::<Vec<_>>();
+        indices.sort();
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+
+    // the same seed gives the same order
+    let other = TrialHandler::new(conditions, options).unwrap();
+    let order = |h: &TrialHandler<_>| h.trials().iter().map(|t| t.condition_index).collect::<Vec<_>>();
+    assert_eq!(order(&handler), order(&other));
+}
+
+#[test]
+fn handlers_without_trials_are_rejected() {
+    let conditions = load_conditions(write_conditions("empty"), b',').unwrap();
+
+    for (n_blocks, n_repetitions) in [(0, 1), (1, 0)] {
+        let options = TrialOptions { n_blocks,
+                                     n_repetitions,
+                                     ..Default::default() };
+        assert!(TrialHandler::new(conditions.clone(), options).is_err());
+    }
+}
+
+#[test]
+fn outcomes_are_logged_until_aborted() {
+    let conditions = load_conditions(write_conditions("logged"), b',').unwrap();
+    let log_path = std::env::temp_dir().join(format!("psybee_trials_log_{}.csv", std::process::id()));
+
+    let options = TrialOptions { n_repetitions: 1,
+                                 n_blocks: 2,
+                                 order: TrialOrder::Sequential,
+                                 seed: None };
+
+    let mut handler = TrialHandler::new(conditions, options).unwrap()
+                                                            .with_logger(log_path.clone(), vec!["response"], true)
+                                                            .unwrap();
+
+    // skip the rest of the first block after one trial
+    let trial = handler.next_trial().unwrap();
+    handler.record(("a",)).unwrap();
+    assert_eq!(trial.block, 0);
+    handler.end_block();
+
+    let trial = handler.next_trial().unwrap();
+    assert_eq!((trial.block, trial.index_in_block), (1, 0));
+    handler.record(("b",)).unwrap();
+    assert!(handler.record(("b", "c")).is_err());
+
+    handler.abort();
+    assert!(handler.next_trial().is_none());
+    assert!(handler.is_finished());
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    let lines = log.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "trial,block,trial_in_block,repetition,condition,contrast,side,response");
+    assert_eq!(lines[1], "0,0,0,0,0,0.1,left,a");
+    assert_eq!(lines[2], "3,1,0,0,0,0.1,left,b");
+    assert_eq!(lines.len(), 3);
+}
@@ -0,0 +1,185 @@
+// Copyright (c) 2024 Marc Pabst
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Tests for trigger outputs.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use psybee::clock::Timestamp;
+use psybee::errors::PsybeeError;
+use psybee::triggers::{LoggingTrigger, NoopTrigger, TriggerOutput};
+use psybee::MainLoop;
+
+#[test]
+fn send_resets_the_output_after_the_pulse_width() {
+    let mut trigger = LoggingTrigger::new();
+
+    // without a pulse width, the code stays on the output
+    trigger.send(1).unwrap();
+
+    trigger.set_pulse_width(Some(Duration::from_millis(5)));
+    let onset = trigger.send(2).unwrap();
+
+    let codes: Vec<u8> = trigger.codes().iter().map(|(_, code)| *code).collect();
+    assert_eq!(codes, vec![1, 2, 0]);
+
+    let (reset, _) = trigger.codes()[2];
+    assert_eq!(trigger.codes()[1].0, onset);
+    assert!(reset.duration_since(onset) >= Duration::from_millis(5));
+}
+
+#[test]
+fn outputs_can_be_used_as_trait_objects() {
+    let mut outputs: Vec<Box<dyn TriggerOutput>> = vec![Box::new(NoopTrigger), Box::new(LoggingTrigger::new())];
+
+    for output in &mut outputs {
+        output.send(42).unwrap();
+        assert_eq!(output.pulse_width(), None);
+    }
+}
+
+/// Records the codes it receives, so they can be checked after the output
+/// has been handed to a window.
+#[derive(Debug, Clone, Default)]
+struct SharedTrigger {
+    codes: Arc<Mutex<Vec<(Timestamp, u8)>>>,
+    pulse_width: Option<Duration>,
+}
+
+impl SharedTrigger {
+    fn codes(&self) -> Vec<u8> {
+        self.codes.lock().unwrap().iter().map(|(_, code)| *code).collect()
+    }
+}
+
+impl TriggerOutput for SharedTrigger {
+    fn write(&mut self, code: u8) -> Result<Timestamp, PsybeeError> {
+        let timestamp = Timestamp::now();
+        self.codes.lock().unwrap().push((timestamp, code));
+        Ok(timestamp)
+    }
+
+    fn pulse_width(&self) -> Option<Duration> {
+        self.pulse_width
+    }
+}
+
+#[test]
+fn frame_triggers_are_sent_after_presentation() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+                 let trigger = SharedTrigger::default();
+                 window.set_trigger_output(Some(Box::new(trigger.clone())));
+
+                 let mut frame = window.get_frame();
+                 frame.add_trigger(7);
+                 frame.add_trigger(8);
+                 let info = window.present(frame);
+
+                 // frames without triggers do not send anything
+                 window.present(window.get_frame());
+
+                 assert_eq!(trigger.codes(), vec![7, 8]);
+
+                 // the codes are recorded with the flip time of the frame
+                 let sent = window.sent_triggers();
+                 assert_eq!(sent.iter().map(|sent| sent.code).collect::<Vec<_>>(), vec![7, 8]);
+                 assert!(sent.iter().all(|sent| sent.frame_index == info.frame_index && sent.flip_time == info.onset()));
+                 assert!(sent[0].write_time.unwrap() >= info.onset());
+                 assert_eq!(sent[0].write_time, Some(trigger.codes.lock().unwrap()[0].0));
+
+                 Ok(())
+             });
+}
+
+#[test]
+fn frame_triggers_are_reset_without_blocking_the_render_task() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+                 let pulse_width = Duration::from_millis(200);
+                 let trigger = SharedTrigger { pulse_width: Some(pulse_width),
+                                               ..Default::default() };
+                 window.set_trigger_output(Some(Box::new(trigger.clone())));
+
+                 let mut frame = window.get_frame();
+                 frame.add_trigger(7);
+                 frame.add_trigger(8);
+                 let before = Timestamp::now();
+                 window.present(frame);
+
+                 // only the first code is written before the frame is handed back
+                 assert!(Timestamp::now().duration_since(before) < pulse_width);
+                 assert_eq!(trigger.codes(), vec![7]);
+
+                 std::thread::sleep(3 * pulse_width);
+                 assert_eq!(trigger.codes(), vec![7, 0, 8, 0]);
+
+                 let sent = window.sent_triggers();
+                 assert!(sent[1].write_time.unwrap().duration_since(sent[0].write_time.unwrap()) >= pulse_width);
+
+                 Ok(())
+             });
+}
+
+#[test]
+fn frame_triggers_of_back_to_back_frames_are_all_sent() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+                 let pulse_width = Duration::from_millis(50);
+                 let trigger = SharedTrigger { pulse_width: Some(pulse_width),
+                                               ..Default::default() };
+                 window.set_trigger_output(Some(Box::new(trigger.clone())));
+
+                 let mut frame = window.get_frame();
+                 frame.add_trigger(1);
+                 frame.add_trigger(2);
+                 let first = window.present(frame);
+
+                 // presented while the codes of the first frame are still being sent
+                 let mut frame = window.get_frame();
+                 frame.add_trigger(3);
+                 let second = window.present(frame);
+
+                 std::thread::sleep(8 * pulse_width);
+                 assert_eq!(trigger.codes(), vec![1, 0, 2, 0, 3, 0]);
+
+                 // the late code is recorded with the time it was actually written
+                 let sent = window.sent_triggers();
+                 assert_eq!(sent.iter().map(|sent| (sent.frame_index, sent.code)).collect::<Vec<_>>(),
+                            vec![(first.frame_index, 1), (first.frame_index, 2), (second.frame_index, 3)]);
+                 assert!(sent[2].write_time.unwrap().duration_since(sent[1].write_time.unwrap()) >= pulse_width);
+
+                 Ok(())
+             });
+}
+
+#[test]
+fn frame_triggers_are_recorded_without_an_output() {
+    let mut main_loop = smol::block_on(MainLoop::new_headless());
+
+    main_loop.run_experiment(|em| {
+                 let window = em.create_offscreen_window(100, 100);
+
+                 let mut frame = window.get_frame();
+                 frame.add_trigger(5);
+                 let info = window.present(frame);
+
+                 let sent = window.sent_triggers();
+                 assert_eq!(sent.len(), 1);
+                 assert_eq!(sent[0].code, 5);
+                 assert_eq!(sent[0].flip_time, info.onset());
+                 assert_eq!(sent[0].write_time, None);
+
+                 Ok(())
+             });
+}
@@ -40,6 +40,10 @@ def my_experiment(exp_manager: ExperimentManager) -> None:
 
         window.present(frame)
 
+        # report dropped frames every 600 frames
+        if i % 600 == 599:
+            print(window.frame_stats())
+
 
 if __name__ == "__main__":
     # Set the logging level